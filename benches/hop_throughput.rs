@@ -0,0 +1,102 @@
+//! Hop throughput of the STFT analysis path: the old per-hop planning approach versus the
+//! cached [`StftAnalyzer`]. Run with `cargo bench --bench hop_throughput`.
+
+use std::time::{Duration, Instant};
+
+use rustfft::{num_complex::Complex32, num_traits::Zero, FftPlanner};
+use spexia::audio::{FftConfig, SpectrumScaling, StftAnalyzer, WindowFunction};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const CHANNELS: usize = 2;
+const MEASURE_FOR: Duration = Duration::from_secs(2);
+
+/// The analysis as it was done before plans were cached: a new planner, window and
+/// buffers for every hop and channel.
+fn analyze_uncached(
+    frame: &[f32],
+    fft_size: usize,
+    spectrum: &mut [Complex32],
+    freq: &mut [f32],
+    time: &mut [f32],
+) {
+    let mut planner: FftPlanner<f32> = FftPlanner::new();
+    let fft = planner.plan_fft_forward(fft_size);
+    let window = WindowFunction::Hann.coefficients(fft_size);
+    let scale = SpectrumScaling::Amplitude.factor(&window);
+
+    let mut data: Vec<_> = (0..fft_size)
+        .map(|i| Complex32::new(frame[i] * window[i] * scale, 0.0))
+        .collect();
+    let mut data_shifted: Vec<_> = (0..fft_size)
+        .map(|i| Complex32::new(frame[i + 1] * window[i], 0.0))
+        .collect();
+    let centre = (fft_size - 1) as f32 / 2.0;
+    let mut data_time: Vec<_> = (0..fft_size)
+        .map(|i| Complex32::new(frame[i] * (i as f32 - centre) * window[i] * scale, 0.0))
+        .collect();
+    fft.process(&mut data);
+    fft.process(&mut data_shifted);
+    fft.process(&mut data_time);
+
+    for i in 0..fft_size / 2 {
+        freq[i] =
+            (data[i].conj() * data_shifted[i]).arg().abs() * SAMPLE_RATE / std::f32::consts::TAU;
+        time[i] = (data_time[i] * data[i].conj()).re
+            / data[i].norm_sqr().max(f32::MIN_POSITIVE)
+            / SAMPLE_RATE;
+    }
+    spectrum.copy_from_slice(&data);
+}
+
+/// Run `hop` repeatedly for [`MEASURE_FOR`] and return hops per second.
+fn measure(mut hop: impl FnMut()) -> f64 {
+    // warm up caches and the allocator
+    for _ in 0..8 {
+        hop();
+    }
+    let start = Instant::now();
+    let mut hops = 0u64;
+    while start.elapsed() < MEASURE_FOR {
+        hop();
+        hops += 1;
+    }
+    hops as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>16} {:>16} {:>8}",
+        "size", "uncached hop/s", "cached hop/s", "speedup"
+    );
+    for size in [512, 2048, 8192, 16384] {
+        let fft_config = FftConfig::new(size, size / 8);
+        let frame: Vec<f32> = (0..=size)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5 + (i as f32 * 0.31).sin() * 0.25)
+            .collect();
+        let mut spectrum = vec![Complex32::zero(); size];
+        let mut freq = vec![0.0; size / 2];
+        let mut time = vec![0.0; size / 2];
+
+        let uncached = measure(|| {
+            for _ in 0..CHANNELS {
+                analyze_uncached(&frame, size, &mut spectrum, &mut freq, &mut time);
+            }
+        });
+
+        let mut analyzer =
+            StftAnalyzer::new(fft_config, WindowFunction::Hann, SpectrumScaling::Amplitude);
+        let cached = measure(|| {
+            for _ in 0..CHANNELS {
+                analyzer.analyze(&frame, SAMPLE_RATE, &mut spectrum, &mut freq, &mut time);
+            }
+        });
+
+        println!(
+            "{:>8} {:>16.0} {:>16.0} {:>7.1}x",
+            size,
+            uncached,
+            cached,
+            cached / uncached
+        );
+    }
+}
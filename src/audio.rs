@@ -1,98 +1,341 @@
 use std::{
     collections::VecDeque,
-    sync::{Arc, Mutex},
-    time::SystemTime,
+    fmt::Display,
+    path::Path,
+    sync::{atomic::Ordering, mpsc, Arc, Mutex},
 };
 
-use cpal::{
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    Error, ErrorKind, Device, Host, Stream, SupportedStreamConfig,
-};
-use rustfft::{num_complex::Complex32, num_traits::Zero, FftPlanner};
+use cpal::{traits::StreamTrait, Device, Error, ErrorKind, Stream, SupportedStreamConfig};
+use rayon::prelude::*;
+use rustfft::{num_complex::Complex32, num_traits::Zero};
+
+mod analysis;
+mod capture;
+mod channels;
+mod cqt;
+mod cwt;
+mod device;
+mod file;
+mod generator;
+mod grid;
+mod multires;
+mod pitch;
+mod synchrosqueeze;
+mod window;
+mod worker;
+mod zoom;
 
-pub const FFT_SIZE: usize = 2048;
-pub const FFT_STRIDE: usize = 256;
-pub const HALF_FFT_SIZE: usize = FFT_SIZE / 2;
+pub use analysis::StftAnalyzer;
+pub use channels::{ChannelMap, ParseChannelMapError};
+pub use cqt::{CqtConfig, ParseCqtConfigError};
+pub use cwt::{CwtConfig, ParseCwtConfigError, ParseWaveletError, Wavelet};
+pub use device::{list_devices, DeviceChoice, DeviceInfo, DeviceSelector, FindDeviceError};
+pub use file::{AudioFile, FileError, FileMode};
+pub use generator::{ParseSignalError, Signal, SignalGenerator};
+use grid::GridAnalyzer;
+pub use grid::{AnalysisMode, GridConfig, SpectrumGrid};
+pub use multires::{MultiResConfig, ParseMultiResConfigError};
+pub use pitch::Pitch;
+use pitch::PitchDetector;
+pub use window::{SpectrumScaling, WindowFunction};
+pub use worker::DiagnosticsSnapshot;
+use worker::{AnalysisCommand, StreamDiagnostics};
+pub use zoom::{ParseZoomConfigError, ZoomConfig};
+
+/// Analysis frame size and hop, adjustable at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FftConfig {
+    pub size: usize,
+    pub stride: usize,
+}
+impl FftConfig {
+    pub const MIN_SIZE: usize = 256;
+    pub const MAX_SIZE: usize = 16384;
+    pub const MIN_STRIDE: usize = 32;
+
+    pub fn new(size: usize, stride: usize) -> Self {
+        let size = size
+            .next_power_of_two()
+            .clamp(Self::MIN_SIZE, Self::MAX_SIZE);
+        let stride = stride.next_power_of_two().clamp(Self::MIN_STRIDE, size);
+        Self { size, stride }
+    }
+    pub fn half_size(&self) -> usize {
+        self.size / 2
+    }
+    /// Double (`up`) or halve the frame size, keeping the hop within the frame.
+    pub fn step_size(self, up: bool) -> Self {
+        if up {
+            Self::new(self.size * 2, self.stride)
+        } else {
+            Self::new(self.size / 2, self.stride)
+        }
+    }
+    /// Double (`up`) or halve the hop between frames.
+    pub fn step_stride(self, up: bool) -> Self {
+        if up {
+            Self::new(self.size, self.stride * 2)
+        } else {
+            Self::new(self.size, self.stride / 2)
+        }
+    }
+}
+impl Default for FftConfig {
+    fn default() -> Self {
+        Self::new(2048, 256)
+    }
+}
 
-pub type AudioDataChunk = Box<(
-    [[Complex32; FFT_SIZE]; 2],
-    [[f32; FFT_SIZE]; 2],
-    [[f32; HALF_FFT_SIZE]; 2],
-)>;
+pub struct AudioDataChunk {
+    pub fft_config: FftConfig,
+    pub sample_rate: f32,
+    /// Full complex spectrum per channel (`fft_config.size` bins), normalized per
+    /// [`SpectrumScaling`] so magnitudes are comparable across windows and sizes.
+    pub spectrum: Vec<Vec<Complex32>>,
+    /// Time-domain samples the spectrum was computed from (`fft_config.size` samples).
+    pub wave: Vec<Vec<f32>>,
+    /// Reassigned (instantaneous) frequency in Hz of the lower half of the bins.
+    pub freq: Vec<Vec<f32>>,
+    /// Reassigned time (group delay) in seconds of the lower half of the bins, relative to
+    /// the centre of the frame. Negative is earlier.
+    pub time: Vec<Vec<f32>>,
+    /// Fundamental of each channel's `wave`, if it has one.
+    pub pitch: Vec<Option<Pitch>>,
+    pub mode: AnalysisMode,
+    /// The hop's grid, for modes that produce one.
+    pub grid: Option<SpectrumGrid>,
+}
+impl AudioDataChunk {
+    /// Number of analysed channels (after applying the [`ChannelMap`]).
+    pub fn channels(&self) -> usize {
+        self.spectrum.len()
+    }
+}
 
+/// Analysis state: buffered samples waiting to fill a frame, and the chunks produced from
+/// them. Owned by the analysis worker thread.
 pub struct StreamData {
-    data: VecDeque<[f32; 2]>,
+    /// Buffered samples of each analysed channel.
+    data: Vec<VecDeque<f32>>,
+    /// Samples of each frame before the ones the STFT reads, for grid analyses that need a
+    /// longer frame.
+    history: usize,
     fft_data: VecDeque<AudioDataChunk>,
+    fft_config: FftConfig,
+    /// One analyzer per channel, so channels can be analysed in parallel.
+    analyzers: Vec<StftAnalyzer>,
+    /// One per channel, like `analyzers`.
+    pitch_detectors: Vec<PitchDetector>,
+    window: WindowFunction,
+    scaling: SpectrumScaling,
+    channel_map: ChannelMap,
+    /// Device channels and their weights mixed into each analysed channel, from
+    /// `channel_map`.
+    channel_sources: Vec<Vec<(usize, f32)>>,
+    input_channels: usize,
+    /// One frame per channel, reused between hops.
+    frames: Vec<Vec<f32>>,
+    mode: AnalysisMode,
+    /// One per channel, like `analyzers`.
+    grid_analyzers: Vec<GridAnalyzer>,
+    grid_config: GridConfig,
+    /// Row frequencies of the grid, if `mode` has one.
+    grid_freqs: Option<Vec<f32>>,
     pub sample_rate: f32,
 }
 impl StreamData {
-    fn new(sample_rate: f32) -> Self {
+    fn new(sample_rate: f32, fft_config: FftConfig) -> Self {
         Self {
-            data: VecDeque::new(),
+            data: vec![],
+            history: 0,
             fft_data: VecDeque::new(),
+            fft_config,
+            analyzers: vec![],
+            pitch_detectors: vec![],
+            window: WindowFunction::default(),
+            scaling: SpectrumScaling::default(),
+            channel_map: ChannelMap::default(),
+            channel_sources: vec![],
+            input_channels: 0,
+            frames: vec![],
+            mode: AnalysisMode::default(),
+            grid_analyzers: vec![],
+            grid_config: GridConfig::default(),
+            grid_freqs: None,
             sample_rate,
         }
     }
-    fn append(&mut self, data: &[f32]) {
-        let window_fn: [f32; FFT_SIZE] = std::array::from_fn(|i| {
-            ((i as f32 / HALF_FFT_SIZE as f32 - 1.0) * 3.14159).cos() + 1.0
+    /// Rebuild the per-channel state after the configuration changed. Buffered samples and
+    /// chunks still queued with the old configuration are dropped.
+    fn rebuild(&mut self) {
+        self.channel_sources = self.channel_map.resolve(self.input_channels);
+        let channels = self.channel_sources.len();
+        self.data = vec![VecDeque::new(); channels];
+        self.history = 0;
+        self.frames = vec![vec![]; channels];
+        self.analyzers = (0..channels)
+            .map(|_| StftAnalyzer::new(self.fft_config, self.window, self.scaling))
+            .collect();
+        self.pitch_detectors = (0..channels)
+            .map(|_| PitchDetector::new(self.fft_config.size))
+            .collect();
+        self.rebuild_grid();
+        self.fft_data.clear();
+    }
+    fn rebuild_grid(&mut self) {
+        // built once and cloned, as some analyzers take a while to set up but can share it
+        let channels = self.analyzers.len();
+        let analyzer = (channels > 0).then(|| {
+            GridAnalyzer::new(
+                self.mode,
+                self.fft_config,
+                self.window,
+                &self.grid_config,
+                self.sample_rate,
+            )
         });
-        for i in 0..data.len() / 2 {
-            self.data.push_back([data[i * 2 + 0], data[i * 2 + 1]]);
-        }
-        while self.data.len() >= FFT_SIZE + 2 {
-            let mut planner: FftPlanner<f32> = FftPlanner::new();
-            let fft = planner.plan_fft_forward(FFT_SIZE);
-            let mut fft_data = Box::new((
-                [[Complex32::zero(); FFT_SIZE]; 2],
-                [[0.0; FFT_SIZE]; 2],
-                [[0.0; HALF_FFT_SIZE]; 2],
-            ));
-
-            for j in 0..2 {
-                let mut data_f32 = [0.0; FFT_SIZE];
-                let mut data_f32_shifted = [0.0; FFT_SIZE];
-                for (i, sample) in self
-                    .data
-                    .iter()
-                    .skip(1)
-                    .take(FFT_SIZE - 1)
-                    .copied()
-                    .enumerate()
-                {
-                    data_f32[i + 1] = sample[j];
-                    data_f32_shifted[i] = sample[j];
-                }
-                data_f32[0] = self.data[0][j];
-                data_f32_shifted[FFT_SIZE - 1] = self.data[FFT_SIZE][j];
-
-                let mut data: Vec<_> = (&data_f32)
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, v)| Complex32::new(*v * window_fn[i], 0.0))
-                    .collect();
-                let mut data_shifted: Vec<_> = (&data_f32_shifted)
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, v)| Complex32::new(*v * window_fn[i], 0.0))
-                    .collect();
-
-                fft.process(&mut data[..]);
-                fft.process(&mut data_shifted[..]);
-
-                for i in 0..FFT_SIZE {
-                    fft_data.0[j][i] = data[i];
+        self.grid_freqs = analyzer.as_ref().and_then(|it| it.freqs(self.sample_rate));
+        self.grid_analyzers = analyzer.map_or(vec![], |it| vec![it; channels]);
+
+        // hops keep their timing when the frame grows or shrinks: buffered samples before the
+        // new frame start are dropped, and zeros stand in for samples from before the start
+        let history = self.grid_analyzers.first().map_or(0, |it| {
+            it.frame_len().saturating_sub(self.fft_config.size + 1)
+        });
+        for samples in &mut self.data {
+            if history > self.history {
+                for _ in self.history..history {
+                    samples.push_front(0.0);
                 }
-                for i in 0..HALF_FFT_SIZE {
-                    fft_data.2[j][i] = (data[i].conj() * data_shifted[i]).arg().abs()
-                        * self.sample_rate
-                        / std::f32::consts::TAU;
+            } else {
+                samples.drain(..self.history - history);
+            }
+        }
+        self.history = history;
+    }
+    /// Start over with a new input device.
+    fn reset(&mut self, sample_rate: f32, input_channels: usize) {
+        self.sample_rate = sample_rate;
+        self.input_channels = input_channels;
+        self.rebuild();
+    }
+    pub fn set_fft_config(&mut self, fft_config: FftConfig) {
+        if fft_config != self.fft_config {
+            self.fft_config = fft_config;
+            self.rebuild();
+        }
+    }
+    pub fn set_window(&mut self, window: WindowFunction, scaling: SpectrumScaling) {
+        self.window = window;
+        self.scaling = scaling;
+        self.analyzers = (0..self.analyzers.len())
+            .map(|_| StftAnalyzer::new(self.fft_config, window, scaling))
+            .collect();
+        self.rebuild_grid();
+    }
+    pub fn set_mode(&mut self, mode: AnalysisMode) {
+        self.mode = mode;
+        self.rebuild_grid();
+    }
+    pub fn set_grid_config(&mut self, grid_config: GridConfig) {
+        if grid_config != self.grid_config {
+            self.grid_config = grid_config;
+            self.rebuild_grid();
+        }
+    }
+    pub fn set_channel_map(&mut self, channel_map: ChannelMap) {
+        self.channel_map = channel_map;
+        self.rebuild();
+    }
+    /// Append interleaved samples and analyse every complete frame.
+    fn append(&mut self, data: impl IntoIterator<Item = f32>) {
+        let fft_config = self.fft_config;
+        // the STFT reads the newest `stft_len` samples of each frame
+        let stft_len = fft_config.size + 1;
+        let frame_len = stft_len + self.history;
+        if self.input_channels == 0 {
+            return;
+        }
+
+        let mut input_frame = vec![0.0; self.input_channels];
+        let mut data = data.into_iter();
+        'frames: loop {
+            for sample in input_frame.iter_mut() {
+                match data.next() {
+                    Some(v) => *sample = v,
+                    None => break 'frames,
                 }
-                fft_data.1[j] = data_f32;
             }
-            for _ in 0..FFT_STRIDE {
-                self.data.pop_front();
+            for (samples, sources) in self.data.iter_mut().zip(&self.channel_sources) {
+                let mix = sources
+                    .iter()
+                    .map(|&(c, weight)| input_frame[c] * weight)
+                    .sum();
+                samples.push_back(mix);
+            }
+        }
+
+        while self.data.first().is_some_and(|it| it.len() >= frame_len) {
+            let channels = self.data.len();
+            let mut fft_data = AudioDataChunk {
+                fft_config,
+                sample_rate: self.sample_rate,
+                spectrum: vec![vec![Complex32::zero(); fft_config.size]; channels],
+                wave: vec![vec![0.0; fft_config.size]; channels],
+                freq: vec![vec![0.0; fft_config.half_size()]; channels],
+                time: vec![vec![0.0; fft_config.half_size()]; channels],
+                pitch: vec![None; channels],
+                mode: self.mode,
+                grid: None,
+            };
+
+            let sample_rate = self.sample_rate;
+            self.analyzers
+                .par_iter_mut()
+                .zip(self.frames.par_iter_mut())
+                .zip(self.data.par_iter_mut())
+                .zip(fft_data.spectrum.par_iter_mut())
+                .zip(fft_data.freq.par_iter_mut())
+                .zip(fft_data.time.par_iter_mut())
+                .zip(fft_data.wave.par_iter_mut())
+                .for_each(
+                    |((((((analyzer, frame), samples), spectrum), freq), time), wave)| {
+                        frame.clear();
+                        frame.extend(samples.range(..frame_len));
+
+                        let frame = &frame[frame_len - stft_len..];
+                        analyzer.analyze(frame, sample_rate, spectrum, freq, time);
+                        wave.copy_from_slice(&frame[..fft_config.size]);
+                        samples.drain(..fft_config.stride);
+                    },
+                );
+
+            self.pitch_detectors
+                .par_iter_mut()
+                .zip(fft_data.wave.par_iter())
+                .zip(fft_data.pitch.par_iter_mut())
+                .for_each(|((detector, wave), pitch)| {
+                    *pitch = detector.estimate(wave, sample_rate);
+                });
+
+            if let Some(freqs) = &self.grid_freqs {
+                let mut magnitude = vec![vec![]; channels];
+                self.grid_analyzers
+                    .par_iter_mut()
+                    .zip(self.frames.par_iter())
+                    .zip(fft_data.spectrum.par_iter())
+                    .zip(fft_data.freq.par_iter())
+                    .zip(magnitude.par_iter_mut())
+                    .for_each(|((((grid_analyzer, frame), spectrum), freq), magnitude)| {
+                        grid_analyzer.analyze(frame, spectrum, freq, sample_rate, magnitude);
+                    });
+                fft_data.grid = Some(SpectrumGrid {
+                    freqs: freqs.clone(),
+                    magnitude,
+                    linear_range: self.grid_analyzers[0].linear_range(),
+                });
             }
+
             self.fft_data.push_back(fft_data);
         }
     }
@@ -101,16 +344,54 @@ impl StreamData {
     }
 }
 
+/// Why no capture stream could be opened.
+#[derive(Debug)]
+pub enum StreamError {
+    /// The host reports no devices at all.
+    NoDevice,
+    /// Every device was tried; each entry is a device name and why it could not be opened.
+    NoUsableDevice(Vec<(String, String)>),
+}
+impl Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoDevice => write!(f, "no audio device available"),
+            Self::NoUsableDevice(attempts) => {
+                write!(f, "could not open any audio device")?;
+                for (device, reason) in attempts {
+                    write!(f, "\n  {}: {}", device, reason)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for StreamError {}
+
 struct StreamerInternalState {
     lost_device: bool,
 }
 type StreamerInternalStateRef = Arc<Mutex<StreamerInternalState>>;
 
+/// Where a [`Streamer`]'s samples come from.
+enum Source {
+    Device(#[allow(unused)] Stream),
+    File(#[allow(unused)] file::FilePlayer),
+}
+
+/// Captures audio from a device (or reads a file) and analyses it on a separate worker
+/// thread.
+///
+/// The cpal callback only copies samples into a wait-free ring buffer; the worker turns them
+/// into [`AudioDataChunk`]s, which are picked up with [`Streamer::take`].
 pub struct Streamer {
-    #[allow(unused)]
-    stream: Stream,
+    source: Option<Source>,
+    /// Name of the device or file `source` reads from.
+    device_name: Option<String>,
     internals: StreamerInternalStateRef,
-    pub data: Arc<Mutex<StreamData>>,
+    commands: mpsc::Sender<AnalysisCommand>,
+    chunks: mpsc::Receiver<AudioDataChunk>,
+    diagnostics: Arc<StreamDiagnostics>,
 }
 impl Streamer {
     fn err_fn(err: Error, internals: StreamerInternalStateRef) {
@@ -119,136 +400,216 @@ impl Streamer {
             internals.lock().unwrap().lost_device = true;
         }
     }
-    pub fn update_stream(&mut self, device_selector: &DeviceSelector) {
-        self.stream = Self::get_stream(self.data.clone(), device_selector, self.internals.clone());
+    /// Reopen the capture stream on the selected device. On failure the old stream is
+    /// dropped and the display stops updating until a later call succeeds.
+    pub fn update_stream(&mut self, device_selector: &DeviceSelector) -> Result<(), StreamError> {
+        self.source = None;
+        self.device_name = None;
+        self.internals.lock().unwrap().lost_device = false;
+        let (stream, device_name) = Self::get_stream(
+            &self.commands,
+            device_selector,
+            self.internals.clone(),
+            self.diagnostics.clone(),
+        )?;
+        self.source = Some(Source::Device(stream));
+        self.device_name = Some(device_name);
+        Ok(())
     }
+    /// Open the selected device, or failing that the next device that can be opened with any
+    /// of its supported configs.
     fn get_stream(
-        stream_data: Arc<Mutex<StreamData>>,
+        commands: &mpsc::Sender<AnalysisCommand>,
         device_selector: &DeviceSelector,
         internals: StreamerInternalStateRef,
-    ) -> Stream {
-        let (device, config) = device_selector.get_device_and_config();
-        let device = device.unwrap();
-        let config = config.unwrap();
-
-        assert_eq!(config.channels(), 2);
-        println!("device name: {}", device);
-        {
-            stream_data.lock().unwrap().sample_rate = config.sample_rate() as f32;
+        diagnostics: Arc<StreamDiagnostics>,
+    ) -> Result<(Stream, String), StreamError> {
+        let devices = device_selector.get_devices();
+        if devices.is_empty() {
+            return Err(StreamError::NoDevice);
         }
 
-        let stream = device
-            .build_input_stream(
-                config.into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    stream_data.lock().unwrap().append(data);
-                },
-                {
-                    let internals = internals.clone();
-                    move |err| Self::err_fn(err, internals.clone())
-                },
-                None,
-            )
-            .unwrap();
-
-        stream.play().unwrap();
-        stream
+        let mut attempts = Vec::new();
+        for device in devices {
+            let configs = device_selector.get_configs_from_device(&device);
+            if configs.is_empty() {
+                attempts.push((device.to_string(), "no supported sample format".to_string()));
+                continue;
+            }
+            let mut last_err = None;
+            for config in configs {
+                match Self::open_stream(&device, &config, internals.clone(), diagnostics.clone()) {
+                    Ok((stream, consumer)) => {
+                        println!(
+                            "device name: {} ({} channels, {} Hz, {})",
+                            device,
+                            config.channels(),
+                            config.sample_rate(),
+                            config.sample_format()
+                        );
+                        commands
+                            .send(AnalysisCommand::NewInput {
+                                samples: consumer,
+                                sample_rate: config.sample_rate() as f32,
+                                channels: config.channels() as usize,
+                                lossless: false,
+                            })
+                            .unwrap();
+                        return Ok((stream, device.to_string()));
+                    }
+                    Err(err) => last_err = Some(err),
+                }
+            }
+            let reason = last_err.map_or_else(String::new, |err| err.to_string());
+            eprintln!("could not open {}: {}", device, reason);
+            attempts.push((device.to_string(), reason));
+        }
+        Err(StreamError::NoUsableDevice(attempts))
+    }
+    fn open_stream(
+        device: &Device,
+        config: &SupportedStreamConfig,
+        internals: StreamerInternalStateRef,
+        diagnostics: Arc<StreamDiagnostics>,
+    ) -> Result<(Stream, rtrb::Consumer<f32>), Error> {
+        let (producer, consumer) =
+            worker::ring_buffer(config.sample_rate() as f32, config.channels() as usize);
+        let stream = capture::build_input_stream(device, config, producer, diagnostics, {
+            move |err| Self::err_fn(err, internals.clone())
+        })?;
+        stream.play()?;
+        Ok((stream, consumer))
     }
 
+    /// Name of the device or file being analysed, or `None` if no device could be opened.
+    pub fn device_name(&self) -> Option<&str> {
+        self.device_name.as_deref()
+    }
     pub fn did_lose_device(&self) -> bool {
         self.internals.lock().unwrap().lost_device
     }
-    pub fn begin(device_selector: &DeviceSelector) -> Result<Self, Box<dyn std::error::Error>> {
-        let data = Arc::new(Mutex::new(StreamData::new(0.0)));
-        let internals = Arc::new(Mutex::new(StreamerInternalState { lost_device: false }));
-        let stream = Self::get_stream(data.clone(), device_selector, internals.clone());
-
-        Ok(Self {
-            stream,
-            data,
-            internals,
-        })
+    pub fn set_fft_config(&self, fft_config: FftConfig) {
+        self.commands
+            .send(AnalysisCommand::FftConfig(fft_config))
+            .unwrap();
     }
-}
-
-pub struct DeviceSelector {
-    use_input: bool,
-    host: Host,
-    current_device: Option<Device>,
-    last_poll: SystemTime,
-}
+    pub fn set_window(&self, window: WindowFunction, scaling: SpectrumScaling) {
+        self.commands
+            .send(AnalysisCommand::Window(window, scaling))
+            .unwrap();
+    }
+    pub fn set_channel_map(&self, channel_map: ChannelMap) {
+        self.commands
+            .send(AnalysisCommand::ChannelMap(channel_map))
+            .unwrap();
+    }
+    pub fn set_mode(&self, mode: AnalysisMode) {
+        self.commands.send(AnalysisCommand::Mode(mode)).unwrap();
+    }
+    pub fn set_grid_config(&self, grid_config: GridConfig) {
+        self.commands
+            .send(AnalysisCommand::GridConfig(grid_config))
+            .unwrap();
+    }
+    /// The next analysed chunk, if the worker has produced one.
+    pub fn take(&self) -> Option<AudioDataChunk> {
+        self.chunks.try_recv().ok()
+    }
+    pub fn diagnostics(&self) -> DiagnosticsSnapshot {
+        self.diagnostics.snapshot()
+    }
+    /// Whether the input has ended (a file was read to the end) and every sample of it has
+    /// been analysed. Chunks may still be waiting in [`Streamer::take`].
+    pub fn input_ended(&self) -> bool {
+        self.diagnostics.input_ended.load(Ordering::Relaxed)
+    }
+    /// Start the analysis worker, without any input yet. Open one with
+    /// [`Streamer::update_stream`] or [`Streamer::open_file`].
+    pub fn new(fft_config: FftConfig) -> Self {
+        let (commands, commands_rx) = mpsc::channel();
+        let (chunks_tx, chunks) = mpsc::sync_channel(worker::CHUNK_QUEUE_LEN);
+        let diagnostics = Arc::new(StreamDiagnostics::default());
+        worker::spawn(fft_config, commands_rx, chunks_tx, diagnostics.clone());
 
-impl DeviceSelector {
-    pub fn new(use_input: bool) -> Self {
-        let mut this = Self {
-            use_input,
-            host: cpal::default_host(),
-            current_device: None,
-            last_poll: SystemTime::now(),
-        };
-        this.current_device = this.get_device();
-        this
-    }
-    pub fn uses_input(&self) -> bool {
-        self.use_input
-    }
-    pub fn set_uses_input(&mut self, use_input: bool) {
-        self.use_input = use_input;
-    }
-    pub fn poll_device_has_changed(&mut self, skip_waiting: bool) -> bool {
-        if skip_waiting {
-            self.last_poll = SystemTime::now();
-            // continue
-        } else {
-            if let Ok(elapsed) = self.last_poll.elapsed() {
-                if elapsed.as_secs() >= 1 {
-                    self.last_poll = SystemTime::now();
-                    // continue
-                } else {
-                    return false;
-                }
-            } else {
-                eprintln!("Failed to get elapsed time since last audio device poll.");
-                self.last_poll = SystemTime::now(); // reset to give it another chance to work.
-                return false;
-            }
+        Self {
+            source: None,
+            device_name: None,
+            internals: Arc::new(Mutex::new(StreamerInternalState { lost_device: false })),
+            commands,
+            chunks,
+            diagnostics,
         }
+    }
+    pub fn begin(
+        device_selector: &DeviceSelector,
+        fft_config: FftConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut this = Self::new(fft_config);
+        this.update_stream(device_selector)?;
+        Ok(this)
+    }
+    /// Analyse the WAV or FLAC file at `path` instead of a live device.
+    pub fn open_file(&mut self, path: &Path, mode: FileMode) -> Result<(), FileError> {
+        self.source = None;
+        self.device_name = None;
+        let file = AudioFile::open(path)?;
+        println!(
+            "file: {} ({} channels, {} Hz)",
+            path.display(),
+            file.channels,
+            file.sample_rate
+        );
 
-        let prev_device = self.current_device.as_ref();
-        let device = self.get_device();
+        let sample_rate = file.sample_rate as f32;
+        let (producer, consumer) = worker::ring_buffer(sample_rate, file.channels);
+        self.commands
+            .send(AnalysisCommand::NewInput {
+                samples: consumer,
+                sample_rate,
+                channels: file.channels,
+                lossless: mode == FileMode::Fast,
+            })
+            .unwrap();
+        let player = file::FilePlayer::start(file, producer, mode, self.diagnostics.clone())?;
 
-        // If there were a better way I would use it.
-        let updated = prev_device.map(|it| it.id().ok()).flatten()
-            != device.as_ref().map(|it| it.id().ok()).flatten();
+        self.source = Some(Source::File(player));
+        self.device_name = path.file_name().map(|it| it.to_string_lossy().into_owned());
+        Ok(())
+    }
+    /// Analyse a synthetic test signal instead of a live device. `mode` paces it like a file.
+    pub fn open_generator(
+        &mut self,
+        mut generator: SignalGenerator,
+        mode: FileMode,
+    ) -> Result<(), FileError> {
+        self.source = None;
+        self.device_name = None;
+        let name = format!("{} at {} dBFS", generator.signal, generator.level);
+        println!("generator: {} ({} Hz)", name, generator.sample_rate);
 
-        if updated {
-            self.current_device = device;
-        }
+        let sample_rate = generator.sample_rate;
+        let channels = SignalGenerator::CHANNELS;
+        let (producer, consumer) = worker::ring_buffer(sample_rate as f32, channels);
+        self.commands
+            .send(AnalysisCommand::NewInput {
+                samples: consumer,
+                sample_rate: sample_rate as f32,
+                channels,
+                lossless: mode == FileMode::Fast,
+            })
+            .unwrap();
+        let read = move |buf: &mut [f32]| Ok(generator.read(buf));
+        let player = file::FilePlayer::start_with(
+            read,
+            channels,
+            sample_rate,
+            producer,
+            mode,
+            self.diagnostics.clone(),
+        )?;
 
-        updated
-    }
-    fn get_device(&self) -> Option<Device> {
-        if self.use_input {
-            self.host.default_input_device()
-        } else {
-            self.host.default_output_device()
-        }
-    }
-    fn get_config_from_device(&self, device: &Device) -> Option<SupportedStreamConfig> {
-        (if self.use_input {
-            device.default_input_config()
-        } else {
-            device.default_output_config()
-        })
-        .ok()
-    }
-
-    pub fn get_device_and_config(&self) -> (Option<Device>, Option<SupportedStreamConfig>) {
-        let device = self.get_device();
-        let config = device
-            .as_ref()
-            .map(|d| self.get_config_from_device(d))
-            .flatten();
-        (device, config)
+        self.source = Some(Source::File(player));
+        self.device_name = Some(name);
+        Ok(())
     }
 }
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+
+use super::{FftConfig, SpectrumScaling, WindowFunction};
+
+/// Short-time Fourier analysis of single frames, with frequency and time reassignment.
+///
+/// Everything that only depends on the configuration (FFT plan, window, scratch space) is
+/// built once in [`StftAnalyzer::new`], so analysing a frame does not allocate.
+pub struct StftAnalyzer {
+    fft_config: FftConfig,
+    fft: Arc<dyn Fft<f32>>,
+    /// Window coefficients with the [`SpectrumScaling`] factor folded in.
+    window: Vec<f32>,
+    /// `window` weighted by each sample's distance in samples from the frame centre, for
+    /// time reassignment.
+    window_time: Vec<f32>,
+    buf: Vec<Complex32>,
+    buf_shifted: Vec<Complex32>,
+    buf_time: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+impl StftAnalyzer {
+    pub fn new(fft_config: FftConfig, window: WindowFunction, scaling: SpectrumScaling) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(fft_config.size);
+        let mut window = window.coefficients(fft_config.size);
+        let scale = scaling.factor(&window);
+        window.iter_mut().for_each(|w| *w *= scale);
+        let centre = (fft_config.size - 1) as f32 / 2.0;
+        let window_time = window
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i as f32 - centre) * w)
+            .collect();
+        Self {
+            fft_config,
+            scratch: vec![Complex32::zero(); fft.get_inplace_scratch_len()],
+            buf: vec![Complex32::zero(); fft_config.size],
+            buf_shifted: vec![Complex32::zero(); fft_config.size],
+            buf_time: vec![Complex32::zero(); fft_config.size],
+            window,
+            window_time,
+            fft,
+        }
+    }
+    pub fn fft_config(&self) -> FftConfig {
+        self.fft_config
+    }
+    /// Samples needed per frame: one more than the FFT size, for the one-sample-shifted
+    /// transform used to reassign frequencies.
+    pub fn frame_len(&self) -> usize {
+        self.fft_config.size + 1
+    }
+
+    /// Analyse `frame` (of [`Self::frame_len`] samples), writing the full spectrum to
+    /// `spectrum`, and for the lower half of the bins the reassigned frequency to `freq` and
+    /// the reassigned time, relative to the frame centre, to `time`.
+    pub fn analyze(
+        &mut self,
+        frame: &[f32],
+        sample_rate: f32,
+        spectrum: &mut [Complex32],
+        freq: &mut [f32],
+        time: &mut [f32],
+    ) {
+        let fft_size = self.fft_config.size;
+        assert_eq!(frame.len(), self.frame_len());
+
+        for i in 0..fft_size {
+            self.buf[i] = Complex32::new(frame[i] * self.window[i], 0.0);
+            self.buf_shifted[i] = Complex32::new(frame[i + 1] * self.window[i], 0.0);
+            self.buf_time[i] = Complex32::new(frame[i] * self.window_time[i], 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        self.fft
+            .process_with_scratch(&mut self.buf_shifted, &mut self.scratch);
+        self.fft
+            .process_with_scratch(&mut self.buf_time, &mut self.scratch);
+
+        for (i, freq) in freq
+            .iter_mut()
+            .enumerate()
+            .take(self.fft_config.half_size())
+        {
+            *freq = (self.buf[i].conj() * self.buf_shifted[i]).arg().abs() * sample_rate
+                / std::f32::consts::TAU;
+        }
+        // group delay: the centre of gravity in time of each bin's energy, in samples from the
+        // frame centre. Bins with next to no energy are left at the centre.
+        let max_offset = self.fft_config.half_size() as f32;
+        for (i, time) in time
+            .iter_mut()
+            .enumerate()
+            .take(self.fft_config.half_size())
+        {
+            let power = self.buf[i].norm_sqr();
+            let offset = if power > f32::MIN_POSITIVE {
+                (self.buf_time[i] * self.buf[i].conj()).re / power
+            } else {
+                0.0
+            };
+            *time = offset.clamp(-max_offset, max_offset) / sample_rate;
+        }
+        spectrum.copy_from_slice(&self.buf);
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use cpal::{
+    traits::DeviceTrait, Device, Error, ErrorKind, FromSample, SampleFormat, SizedSample, Stream,
+    SupportedStreamConfig, I24, U24,
+};
+
+use super::worker::{self, StreamDiagnostics};
+
+/// Whether [`build_input_stream`] can convert samples of `format` to f32.
+pub fn is_supported(format: SampleFormat) -> bool {
+    matches!(
+        format,
+        SampleFormat::I8
+            | SampleFormat::I16
+            | SampleFormat::I24
+            | SampleFormat::I32
+            | SampleFormat::I64
+            | SampleFormat::U8
+            | SampleFormat::U16
+            | SampleFormat::U24
+            | SampleFormat::U32
+            | SampleFormat::U64
+            | SampleFormat::F32
+            | SampleFormat::F64
+    )
+}
+
+/// Build an input stream for `config` that converts whatever the device delivers to f32 and
+/// pushes it into `samples`.
+pub fn build_input_stream(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    samples: rtrb::Producer<f32>,
+    diagnostics: Arc<StreamDiagnostics>,
+    error_callback: impl FnMut(Error) + Send + 'static,
+) -> Result<Stream, Error> {
+    match config.sample_format() {
+        SampleFormat::I8 => build::<i8>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::I16 => build::<i16>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::I24 => build::<I24>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::I32 => build::<i32>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::I64 => build::<i64>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::U8 => build::<u8>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::U16 => build::<u16>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::U24 => build::<U24>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::U32 => build::<u32>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::U64 => build::<u64>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::F32 => build::<f32>(device, config, samples, diagnostics, error_callback),
+        SampleFormat::F64 => build::<f64>(device, config, samples, diagnostics, error_callback),
+        format => Err(Error::with_message(
+            ErrorKind::UnsupportedConfig,
+            format!("unsupported sample format {}", format),
+        )),
+    }
+}
+
+fn build<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    mut samples: rtrb::Producer<f32>,
+    diagnostics: Arc<StreamDiagnostics>,
+    error_callback: impl FnMut(Error) + Send + 'static,
+) -> Result<Stream, Error>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device.build_input_stream(
+        config.config(),
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            worker::push_samples(&mut samples, data, &diagnostics);
+        },
+        error_callback,
+        None,
+    )
+}
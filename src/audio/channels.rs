@@ -0,0 +1,118 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Which device channels are analysed, and how they are combined.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ChannelMap {
+    /// Every device channel, in order.
+    #[default]
+    All,
+    /// The average of all device channels.
+    Mono,
+    /// Mid (the average of the first two device channels) and side (half their difference).
+    MidSide,
+    /// Each entry is one analysed channel, averaging the listed device channels (0-based).
+    Custom(Vec<Vec<usize>>),
+}
+impl ChannelMap {
+    /// Device channel indices and their weights making up each analysed channel, for a
+    /// device with `input_channels` channels. Out-of-range channels are ignored; if nothing
+    /// is left, all channels are used instead.
+    pub fn resolve(&self, input_channels: usize) -> Vec<Vec<(usize, f32)>> {
+        let all = || (0..input_channels).map(|c| vec![(c, 1.0)]).collect();
+        let average = |sources: &[usize]| {
+            let weight = 1.0 / sources.len() as f32;
+            sources.iter().map(|&c| (c, weight)).collect()
+        };
+        match self {
+            Self::All => all(),
+            Self::Mono => vec![average(&(0..input_channels).collect::<Vec<_>>())],
+            Self::MidSide if input_channels < 2 => {
+                eprintln!("mid/side needs two channels, using all channels");
+                all()
+            }
+            Self::MidSide => vec![vec![(0, 0.5), (1, 0.5)], vec![(0, 0.5), (1, -0.5)]],
+            Self::Custom(mapping) => {
+                let mapping: Vec<Vec<usize>> = mapping
+                    .iter()
+                    .map(|sources| {
+                        sources
+                            .iter()
+                            .copied()
+                            .filter(|&c| c < input_channels)
+                            .collect::<Vec<_>>()
+                    })
+                    .filter(|sources| !sources.is_empty())
+                    .collect();
+                if mapping.is_empty() {
+                    eprintln!(
+                        "channel map {} does not fit a {} channel device, using all channels",
+                        self, input_channels
+                    );
+                    all()
+                } else {
+                    mapping.iter().map(|sources| average(sources)).collect()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseChannelMapError(String);
+impl Display for ParseChannelMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid channel map {:?}, expected \"all\", \"mono\", \"midside\" or 1-based channels like \"3,4\" or \"1+2,3\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseChannelMapError {}
+
+impl FromStr for ChannelMap {
+    type Err = ParseChannelMapError;
+    /// `all`, `mono`, `midside`, or a comma-separated list of 1-based device channels, where
+    /// `a+b` mixes several device channels into one analysed channel.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => return Ok(Self::All),
+            "mono" => return Ok(Self::Mono),
+            "midside" => return Ok(Self::MidSide),
+            _ => {}
+        }
+        let err = || ParseChannelMapError(s.to_string());
+        s.split(',')
+            .map(|sources| {
+                sources
+                    .split('+')
+                    .map(|c| match c.trim().parse::<usize>() {
+                        Ok(c) if c > 0 => Ok(c - 1),
+                        _ => Err(err()),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::Custom)
+    }
+}
+impl Display for ChannelMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Mono => write!(f, "mono"),
+            Self::MidSide => write!(f, "midside"),
+            Self::Custom(mapping) => {
+                let channels: Vec<String> = mapping
+                    .iter()
+                    .map(|sources| {
+                        let sources: Vec<String> =
+                            sources.iter().map(|c| (c + 1).to_string()).collect();
+                        sources.join("+")
+                    })
+                    .collect();
+                write!(f, "{}", channels.join(","))
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{f32::consts::TAU, fmt::Display, str::FromStr, sync::Arc};
+
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+
+/// Longest frame in samples; wavelets at the lowest scales are cut short beyond it.
+const MAX_FRAME: usize = 1 << 16;
+/// Kernel spectrum values below this fraction of the peak are dropped.
+const KERNEL_THRESHOLD: f32 = 5e-3;
+
+/// Mother wavelet of the continuous wavelet transform. All are used in their analytic form
+/// (positive frequencies only), so the magnitude is a smooth envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wavelet {
+    /// A Gabor atom with `omega0` radians per envelope standard deviation: larger values give
+    /// finer frequency and coarser time resolution.
+    Morlet { omega0: f32 },
+    /// Second derivative of a Gaussian: very short in time, about an octave wide.
+    MexicanHat,
+}
+impl Default for Wavelet {
+    fn default() -> Self {
+        Self::Morlet {
+            omega0: Self::MORLET_OMEGA0,
+        }
+    }
+}
+impl Wavelet {
+    const MORLET_OMEGA0: f32 = 6.0;
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Morlet { omega0 } => format!("morlet(ω0={omega0})"),
+            Self::MexicanHat => "mexican-hat".into(),
+        }
+    }
+    /// The next wavelet in the selection cycle, with default parameters.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Morlet { .. } => Self::MexicanHat,
+            Self::MexicanHat => Self::default(),
+        }
+    }
+    /// The wavelet's spectrum at `ratio` times its peak frequency, 1 at the peak.
+    fn spectrum(&self, ratio: f32) -> f32 {
+        match self {
+            Self::Morlet { omega0 } => (-(omega0 * (ratio - 1.0)).powi(2) / 2.0).exp(),
+            Self::MexicanHat => ratio * ratio * (1.0 - ratio * ratio).exp(),
+        }
+    }
+    /// Ratio to the peak frequency above which the spectrum is below 1% of its peak.
+    fn upper_extent(&self) -> f32 {
+        match self {
+            Self::Morlet { omega0 } => 1.0 + 3.035 / omega0,
+            Self::MexicanHat => 2.77,
+        }
+    }
+    /// Half the wavelet's length in seconds (where it has decayed to next to nothing), at
+    /// peak frequency `freq`.
+    fn half_support(&self, freq: f32) -> f32 {
+        match self {
+            // four standard deviations of the Gaussian envelope
+            Self::Morlet { omega0 } => 4.0 * omega0 / (TAU * freq),
+            // five for the Mexican hat, whose polynomial factor fattens the tails
+            Self::MexicanHat => 5.0 * 2f32.sqrt() / (TAU * freq),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseWaveletError(String);
+impl Display for ParseWaveletError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown wavelet {:?}, expected morlet, morlet:<omega0> or mexican-hat",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseWaveletError {}
+
+impl FromStr for Wavelet {
+    type Err = ParseWaveletError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseWaveletError(s.to_string());
+        match s.split_once(':') {
+            None if s == "morlet" => Ok(Self::default()),
+            None if s == "mexican-hat" || s == "mexhat" => Ok(Self::MexicanHat),
+            Some(("morlet", omega0)) => match omega0.parse() {
+                Ok(omega0) if omega0 > 0.0 => Ok(Self::Morlet { omega0 }),
+                _ => Err(err()),
+            },
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Wavelet and scales of the continuous wavelet transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CwtConfig {
+    pub wavelet: Wavelet,
+    pub voices_per_octave: usize,
+    /// Peak frequency of the largest scale in Hz.
+    pub min_freq: f32,
+    /// Scales stop at the first peak frequency above this, or where the wavelet would reach
+    /// Nyquist.
+    pub max_freq: f32,
+}
+impl Default for CwtConfig {
+    fn default() -> Self {
+        Self {
+            wavelet: Wavelet::default(),
+            voices_per_octave: 24,
+            // C1 to C10
+            min_freq: 32.703,
+            max_freq: 16744.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseCwtConfigError(String);
+impl Display for ParseCwtConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid wavelet scales {:?}, expected <voices per octave>,<min hz>,<max hz>, \
+             like \"24,32.7,16744\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseCwtConfigError {}
+
+impl CwtConfig {
+    /// Parse the scales, `voices,min,max`, keeping the wavelet.
+    pub fn with_scales(self, s: &str) -> Result<Self, ParseCwtConfigError> {
+        let err = || ParseCwtConfigError(s.to_string());
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [voices, min, max] = parts[..] else {
+            return Err(err());
+        };
+        let config = Self {
+            voices_per_octave: voices.parse().map_err(|_| err())?,
+            min_freq: min.parse().map_err(|_| err())?,
+            max_freq: max.parse().map_err(|_| err())?,
+            ..self
+        };
+        if config.voices_per_octave == 0
+            || config.min_freq <= 0.0
+            || config.max_freq < config.min_freq
+        {
+            return Err(err());
+        }
+        Ok(config)
+    }
+    /// Peak frequency in Hz of every scale.
+    fn freqs(&self, sample_rate: f32) -> Vec<f32> {
+        let limit = sample_rate / 2.0 / self.wavelet.upper_extent();
+        (0..)
+            .map(|k| self.min_freq * (k as f32 / self.voices_per_octave as f32).exp2())
+            .take_while(|&freq| freq <= self.max_freq && freq < limit)
+            .collect()
+    }
+}
+
+/// One scale's kernel in the frequency domain, like the constant-Q transform's.
+struct SpectralKernel {
+    start: usize,
+    values: Vec<Complex32>,
+}
+
+/// Continuous wavelet transform at one instant per hop.
+///
+/// Evaluating a single time point, the transform of each scale is the dot product of the
+/// frame's spectrum with the wavelet's (known in closed form), so a hop costs one FFT and a
+/// short sparse product per scale. Each scale is evaluated where its wavelet just fits before
+/// the newest sample, so the small scales are not delayed by the large ones.
+#[derive(Clone)]
+pub struct WaveletTransform {
+    fft: Arc<dyn Fft<f32>>,
+    kernels: Arc<Vec<SpectralKernel>>,
+    freqs: Arc<Vec<f32>>,
+    buf: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+impl WaveletTransform {
+    pub fn new(config: CwtConfig, sample_rate: f32) -> Self {
+        let freqs = config.freqs(sample_rate);
+        let wavelet = config.wavelet;
+        let half_support = |freq: f32| (wavelet.half_support(freq) * sample_rate).ceil() as usize;
+        let frame_len = freqs
+            .first()
+            .map_or(2, |&freq| 2 * half_support(freq) + 1)
+            .next_power_of_two()
+            .min(MAX_FRAME);
+        let bin_hz = sample_rate / frame_len as f32;
+
+        let kernels = freqs
+            .iter()
+            .map(|&freq| {
+                // centred half a wavelet before the newest sample, scaled so a full scale
+                // sinusoid at the peak frequency gives 1
+                let centre = frame_len.saturating_sub(half_support(freq) + 1);
+                let spectrum = |j: usize| wavelet.spectrum(j as f32 * bin_hz / freq);
+                let value = |j: usize| {
+                    // wrapped in integers first, as j * centre is too large for an f32 phase
+                    let phase = TAU * ((j * centre) % frame_len) as f32 / frame_len as f32;
+                    Complex32::from_polar(2.0 * spectrum(j) / frame_len as f32, phase)
+                };
+                let significant = |j: &usize| spectrum(*j) >= KERNEL_THRESHOLD;
+                let start = (1..frame_len / 2).find(significant).unwrap_or(0);
+                let end = (start..frame_len / 2)
+                    .rev()
+                    .find(significant)
+                    .map_or(start, |it| it + 1);
+                SpectralKernel {
+                    start,
+                    values: (start..end).map(value).collect(),
+                }
+            })
+            .collect();
+
+        let fft = FftPlanner::new().plan_fft_forward(frame_len);
+        Self {
+            scratch: vec![Complex32::zero(); fft.get_inplace_scratch_len()],
+            buf: vec![Complex32::zero(); frame_len],
+            fft,
+            kernels: Arc::new(kernels),
+            freqs: Arc::new(freqs),
+        }
+    }
+    /// Samples needed per frame.
+    pub fn frame_len(&self) -> usize {
+        self.buf.len()
+    }
+    /// Number of scales, one output row each.
+    pub fn scales(&self) -> usize {
+        self.freqs.len()
+    }
+    /// Peak frequency in Hz of each scale, ascending.
+    pub fn freqs(&self) -> Vec<f32> {
+        self.freqs.to_vec()
+    }
+    /// Transform the last [`Self::frame_len`] samples of `frame` into `magnitude`, one row
+    /// per scale.
+    pub fn analyze(&mut self, frame: &[f32], magnitude: &mut [f32]) {
+        let frame = &frame[frame.len() - self.buf.len()..];
+        for (buf, &sample) in self.buf.iter_mut().zip(frame) {
+            *buf = Complex32::new(sample, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        for (magnitude, kernel) in magnitude.iter_mut().zip(self.kernels.iter()) {
+            let spectrum = &self.buf[kernel.start..kernel.start + kernel.values.len()];
+            let value: Complex32 = spectrum
+                .iter()
+                .zip(&kernel.values)
+                .map(|(x, k)| x * k)
+                .sum();
+            *magnitude = value.norm();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAVELETS: [Wavelet; 3] = [
+        Wavelet::Morlet { omega0: 6.0 },
+        Wavelet::Morlet { omega0: 12.0 },
+        Wavelet::MexicanHat,
+    ];
+
+    #[test]
+    fn spectrum_is_below_1_percent_past_upper_extent() {
+        for wavelet in WAVELETS {
+            let extent = wavelet.upper_extent();
+            for ratio in [extent, extent * 1.1, extent * 2.0] {
+                let spectrum = wavelet.spectrum(ratio);
+                assert!(
+                    spectrum <= 0.01,
+                    "{}: {spectrum} at {ratio}",
+                    wavelet.name()
+                );
+            }
+            // and not by much
+            assert!(wavelet.spectrum(extent) > 0.009, "{}", wavelet.name());
+        }
+    }
+
+    #[test]
+    fn sine_at_peak_frequency_reads_0_dbfs() {
+        let sample_rate = 48000.0;
+        for wavelet in WAVELETS {
+            let config = CwtConfig {
+                wavelet,
+                min_freq: 220.0,
+                ..CwtConfig::default()
+            };
+            let mut cwt = WaveletTransform::new(config, sample_rate);
+            let freqs = cwt.freqs();
+            for k in [0, freqs.len() / 2, freqs.len() - 1] {
+                let step = std::f64::consts::TAU * freqs[k] as f64 / sample_rate as f64;
+                let frame: Vec<f32> = (0..cwt.frame_len())
+                    .map(|i| (step * i as f64).sin() as f32)
+                    .collect();
+                let mut magnitude = vec![0.0; cwt.scales()];
+                cwt.analyze(&frame, &mut magnitude);
+                let db = 20.0 * magnitude[k].log10();
+                assert!(
+                    db.abs() < 0.05,
+                    "{}, {} Hz: {db} dBFS",
+                    wavelet.name(),
+                    freqs[k]
+                );
+            }
+        }
+    }
+}
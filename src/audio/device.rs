@@ -0,0 +1,293 @@
+use std::{fmt::Display, str::FromStr, time::SystemTime};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait},
+    Device, DeviceId, Host, SupportedStreamConfig, SupportedStreamConfigRange,
+};
+
+use super::capture;
+
+/// Which device to capture from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DeviceChoice {
+    /// Follow the default input (or output) device of the default host, switching when it
+    /// changes.
+    #[default]
+    Default,
+    /// One specific device, on any host.
+    Device(DeviceId),
+}
+impl DeviceChoice {
+    /// Look up `query` among the devices listed by [`list_devices`]: `default`, a 1-based
+    /// index into that list, a full device id, or a case-insensitive part of a device name.
+    pub fn find(query: &str, use_input: bool) -> Result<Self, FindDeviceError> {
+        if query == "default" {
+            return Ok(Self::Default);
+        }
+        let devices = list_devices(use_input);
+        if let Ok(index) = query.parse::<usize>() {
+            return match index.checked_sub(1).and_then(|i| devices.get(i)) {
+                Some(device) => Ok(Self::Device(device.id.clone())),
+                None => Err(FindDeviceError::NotFound(query.to_string())),
+            };
+        }
+        if let Some(device) = devices.iter().find(|it| it.id.to_string() == query) {
+            return Ok(Self::Device(device.id.clone()));
+        }
+        let lower = query.to_lowercase();
+        let matches: Vec<&DeviceInfo> = devices
+            .iter()
+            .filter(|it| it.name.to_lowercase().contains(&lower))
+            .collect();
+        match matches.as_slice() {
+            [] => Err(FindDeviceError::NotFound(query.to_string())),
+            [device] => Ok(Self::Device(device.id.clone())),
+            _ => Err(FindDeviceError::Ambiguous(
+                query.to_string(),
+                matches.iter().map(|it| it.to_string()).collect(),
+            )),
+        }
+    }
+}
+/// `default` or the device id, as stored in the settings file.
+impl Display for DeviceChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Device(id) => write!(f, "{}", id),
+        }
+    }
+}
+impl FromStr for DeviceChoice {
+    type Err = cpal::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            _ => s.parse().map(Self::Device),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FindDeviceError {
+    NotFound(String),
+    /// The query and every device it matched.
+    Ambiguous(String, Vec<String>),
+}
+impl Display for FindDeviceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(query) => write!(
+                f,
+                "no audio device matches {:?} (see --list-devices)",
+                query
+            ),
+            Self::Ambiguous(query, matches) => {
+                write!(f, "{:?} matches several audio devices:", query)?;
+                for device in matches {
+                    write!(f, "\n  {}", device)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for FindDeviceError {}
+
+/// A device found by [`list_devices`].
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub id: DeviceId,
+    pub name: String,
+}
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.id)
+    }
+}
+
+/// Every input (or output) device on every available host. Hosts that fail to initialise
+/// are skipped.
+pub fn list_devices(use_input: bool) -> Vec<DeviceInfo> {
+    let mut devices = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+        let host_devices: Vec<Device> = if use_input {
+            host.input_devices().map(|it| it.collect())
+        } else {
+            host.output_devices().map(|it| it.collect())
+        }
+        .unwrap_or_default();
+        for device in host_devices {
+            if let Ok(id) = device.id() {
+                devices.push(DeviceInfo {
+                    id,
+                    name: device.to_string(),
+                });
+            }
+        }
+    }
+    devices
+}
+
+pub struct DeviceSelector {
+    use_input: bool,
+    host: Host,
+    choice: DeviceChoice,
+    current_device: Option<Device>,
+    last_poll: SystemTime,
+}
+
+impl DeviceSelector {
+    pub fn new(use_input: bool, choice: DeviceChoice) -> Self {
+        let mut this = Self {
+            use_input,
+            host: cpal::default_host(),
+            choice,
+            current_device: None,
+            last_poll: SystemTime::now(),
+        };
+        this.current_device = this.get_device();
+        this
+    }
+    pub fn uses_input(&self) -> bool {
+        self.use_input
+    }
+    /// Switch between capturing inputs and monitoring outputs. Goes back to following the
+    /// default device, since a chosen device usually only exists in one direction.
+    pub fn set_uses_input(&mut self, use_input: bool) {
+        self.use_input = use_input;
+        self.set_choice(DeviceChoice::Default);
+    }
+    pub fn choice(&self) -> &DeviceChoice {
+        &self.choice
+    }
+    pub fn set_choice(&mut self, choice: DeviceChoice) {
+        self.choice = choice;
+        self.current_device = self.get_device();
+    }
+    /// Step through following the default device and then each device from
+    /// [`list_devices`], wrapping around.
+    pub fn cycle(&mut self, forward: bool) {
+        let choices: Vec<DeviceChoice> = std::iter::once(DeviceChoice::Default)
+            .chain(
+                list_devices(self.use_input)
+                    .into_iter()
+                    .map(|it| DeviceChoice::Device(it.id)),
+            )
+            .collect();
+        let i = choices
+            .iter()
+            .position(|it| *it == self.choice)
+            .unwrap_or(0);
+        let i = if forward {
+            (i + 1) % choices.len()
+        } else {
+            (i + choices.len() - 1) % choices.len()
+        };
+        self.set_choice(choices[i].clone());
+    }
+    pub fn poll_device_has_changed(&mut self, skip_waiting: bool) -> bool {
+        if skip_waiting {
+            self.last_poll = SystemTime::now();
+            // continue
+        } else {
+            if let Ok(elapsed) = self.last_poll.elapsed() {
+                if elapsed.as_secs() >= 1 {
+                    self.last_poll = SystemTime::now();
+                    // continue
+                } else {
+                    return false;
+                }
+            } else {
+                eprintln!("Failed to get elapsed time since last audio device poll.");
+                self.last_poll = SystemTime::now(); // reset to give it another chance to work.
+                return false;
+            }
+        }
+
+        let prev_device = self.current_device.as_ref();
+        let device = self.get_device();
+
+        // If there were a better way I would use it.
+        let updated = prev_device.map(|it| it.id().ok()).flatten()
+            != device.as_ref().map(|it| it.id().ok()).flatten();
+
+        if updated {
+            self.current_device = device;
+        }
+
+        updated
+    }
+    fn get_device(&self) -> Option<Device> {
+        match &self.choice {
+            DeviceChoice::Default => self.get_default_device(),
+            DeviceChoice::Device(id) => cpal::host_from_id(id.host()).ok()?.device_by_id(id),
+        }
+    }
+    fn get_default_device(&self) -> Option<Device> {
+        if self.use_input {
+            self.host.default_input_device()
+        } else {
+            self.host.default_output_device()
+        }
+    }
+    /// The selected device first, then the default device and every other device of the
+    /// default host as fallbacks.
+    pub(super) fn get_devices(&self) -> Vec<Device> {
+        let mut devices: Vec<Device> = self.get_device().into_iter().collect();
+        let others = if self.use_input {
+            self.host.input_devices().map(|it| it.collect::<Vec<_>>())
+        } else {
+            self.host.output_devices().map(|it| it.collect::<Vec<_>>())
+        };
+        let fallbacks = self
+            .get_default_device()
+            .into_iter()
+            .chain(others.unwrap_or_default());
+        for device in fallbacks {
+            let id = device.id().ok();
+            if id.is_none() || devices.iter().all(|it| it.id().ok() != id) {
+                devices.push(device);
+            }
+        }
+        devices
+    }
+    /// Configs worth trying on `device`, best first: the default config if we can convert its
+    /// sample format, then the supported config ranges in cpal's order of preference, at the
+    /// default sample rate where possible.
+    pub(super) fn get_configs_from_device(&self, device: &Device) -> Vec<SupportedStreamConfig> {
+        let (default, ranges) = if self.use_input {
+            (
+                device.default_input_config().ok(),
+                device.supported_input_configs().map(|it| it.collect()),
+            )
+        } else {
+            (
+                device.default_output_config().ok(),
+                device.supported_output_configs().map(|it| it.collect()),
+            )
+        };
+        let mut ranges: Vec<SupportedStreamConfigRange> = ranges.unwrap_or_default();
+        ranges.retain(|it| capture::is_supported(it.sample_format()));
+        ranges.sort_by(|a, b| b.cmp_default_heuristics(a));
+
+        let default_rate = default.as_ref().map(|it| it.sample_rate());
+        let mut configs: Vec<SupportedStreamConfig> = default
+            .into_iter()
+            .filter(|it| capture::is_supported(it.sample_format()))
+            .collect();
+        for range in ranges {
+            let config = default_rate
+                .and_then(|rate| range.try_with_sample_rate(rate))
+                .or_else(|| range.try_with_standard_sample_rate())
+                .unwrap_or_else(|| range.with_max_sample_rate());
+            if !configs.contains(&config) {
+                configs.push(config);
+            }
+        }
+        configs
+    }
+}
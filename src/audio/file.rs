@@ -0,0 +1,427 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    Device, FromSample, SampleFormat, SizedSample, Stream, SupportedStreamConfig,
+};
+
+use super::{
+    capture,
+    worker::{self, StreamDiagnostics},
+};
+
+/// Frames decoded per read; also how finely real-time feeding is paced.
+const BLOCK_FRAMES: usize = 1024;
+/// Seconds of decoded audio queued for playback. Analysis follows what is heard, so this is
+/// kept short.
+const PLAYBACK_SECONDS: f32 = 0.1;
+/// How long the feeder waits when it is ahead of real time or a ring buffer is full.
+const FEED_SLEEP: Duration = Duration::from_millis(2);
+
+/// How fast a file is fed to the analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileMode {
+    /// At the file's sample rate, as if it were captured live.
+    #[default]
+    Realtime,
+    /// In real time, paced by playing the file on the default output device.
+    Playback,
+    /// As fast as the analysis keeps up, without dropping any hops. For offline rendering.
+    Fast,
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io(std::io::Error),
+    Wav(hound::Error),
+    Flac(claxon::Error),
+    /// Neither a RIFF/WAVE nor a FLAC file.
+    UnknownFormat,
+    /// `--playback` needs an output device running at the file's sample rate.
+    Playback(String),
+}
+impl Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read audio file: {}", err),
+            Self::Wav(err) => write!(f, "could not decode wav file: {}", err),
+            Self::Flac(err) => write!(f, "could not decode flac file: {}", err),
+            Self::UnknownFormat => write!(f, "unsupported audio file, expected wav or flac"),
+            Self::Playback(message) => write!(f, "cannot play back file: {}", message),
+        }
+    }
+}
+impl std::error::Error for FileError {}
+impl From<std::io::Error> for FileError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+impl From<hound::Error> for FileError {
+    fn from(err: hound::Error) -> Self {
+        Self::Wav(err)
+    }
+}
+impl From<claxon::Error> for FileError {
+    fn from(err: claxon::Error) -> Self {
+        Self::Flac(err)
+    }
+}
+
+enum Decoder {
+    WavInt {
+        samples: hound::WavIntoSamples<BufReader<File>, i32>,
+        scale: f32,
+    },
+    WavFloat(hound::WavIntoSamples<BufReader<File>, f32>),
+    Flac {
+        reader: claxon::FlacReader<File>,
+        scale: f32,
+        /// Interleaved samples of the last decoded block not yet handed out.
+        pending: Vec<f32>,
+        pending_pos: usize,
+        block_buffer: Vec<i32>,
+    },
+}
+
+/// A WAV (integer PCM or float) or FLAC file, decoded incrementally to interleaved f32.
+pub struct AudioFile {
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Length in frames, if the header says.
+    pub frames: Option<u64>,
+    decoder: Decoder,
+}
+impl AudioFile {
+    pub fn open(path: &Path) -> Result<Self, FileError> {
+        let mut magic = [0; 4];
+        File::open(path)?.read_exact(&mut magic)?;
+        match &magic {
+            b"RIFF" => {
+                let reader = hound::WavReader::open(path)?;
+                let spec = reader.spec();
+                let frames = reader.duration() as u64;
+                let decoder = match spec.sample_format {
+                    hound::SampleFormat::Int => Decoder::WavInt {
+                        scale: 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32,
+                        samples: reader.into_samples(),
+                    },
+                    hound::SampleFormat::Float => Decoder::WavFloat(reader.into_samples()),
+                };
+                Ok(Self {
+                    sample_rate: spec.sample_rate,
+                    channels: spec.channels as usize,
+                    frames: Some(frames),
+                    decoder,
+                })
+            }
+            b"fLaC" => {
+                let reader = claxon::FlacReader::open(path)?;
+                let info = reader.streaminfo();
+                Ok(Self {
+                    sample_rate: info.sample_rate,
+                    channels: info.channels as usize,
+                    frames: info.samples,
+                    decoder: Decoder::Flac {
+                        reader,
+                        scale: 1.0 / (1u64 << (info.bits_per_sample - 1)) as f32,
+                        pending: Vec::new(),
+                        pending_pos: 0,
+                        block_buffer: Vec::new(),
+                    },
+                })
+            }
+            _ => Err(FileError::UnknownFormat),
+        }
+    }
+
+    /// Decode up to `out.len()` interleaved samples. Returns how many were written; 0 means
+    /// the end of the file.
+    pub fn read(&mut self, out: &mut [f32]) -> Result<usize, FileError> {
+        match &mut self.decoder {
+            Decoder::WavInt { samples, scale } => {
+                let mut n = 0;
+                for (out, sample) in out.iter_mut().zip(samples) {
+                    *out = sample? as f32 * *scale;
+                    n += 1;
+                }
+                Ok(n)
+            }
+            Decoder::WavFloat(samples) => {
+                let mut n = 0;
+                for (out, sample) in out.iter_mut().zip(samples) {
+                    *out = sample?;
+                    n += 1;
+                }
+                Ok(n)
+            }
+            Decoder::Flac {
+                reader,
+                scale,
+                pending,
+                pending_pos,
+                block_buffer,
+            } => {
+                if *pending_pos == pending.len() {
+                    let buffer = std::mem::take(block_buffer);
+                    let Some(block) = reader.blocks().read_next_or_eof(buffer)? else {
+                        return Ok(0);
+                    };
+                    pending.clear();
+                    for i in 0..block.duration() {
+                        for ch in 0..block.channels() {
+                            pending.push(block.sample(ch, i) as f32 * *scale);
+                        }
+                    }
+                    *pending_pos = 0;
+                    *block_buffer = block.into_buffer();
+                }
+                let n = out.len().min(pending.len() - *pending_pos);
+                out[..n].copy_from_slice(&pending[*pending_pos..*pending_pos + n]);
+                *pending_pos += n;
+                Ok(n)
+            }
+        }
+    }
+}
+
+/// Feeds an [`AudioFile`] (or any other finite or endless source of interleaved samples, like
+/// a [`SignalGenerator`](super::SignalGenerator)) into a capture ring buffer from its own
+/// thread, optionally playing it back. Stops when dropped.
+pub struct FilePlayer {
+    stop: Arc<AtomicBool>,
+    feeder: Option<JoinHandle<()>>,
+    #[allow(unused)]
+    playback: Option<Stream>,
+}
+impl FilePlayer {
+    /// Start feeding `file` into `samples`. Once the whole file is pushed, `samples` is
+    /// dropped, which the analysis worker sees as the end of the input.
+    pub fn start(
+        mut file: AudioFile,
+        samples: rtrb::Producer<f32>,
+        mode: FileMode,
+        diagnostics: Arc<StreamDiagnostics>,
+    ) -> Result<Self, FileError> {
+        let (channels, sample_rate) = (file.channels, file.sample_rate);
+        let read = move |buf: &mut [f32]| file.read(buf);
+        Self::start_with(read, channels, sample_rate, samples, mode, diagnostics)
+    }
+
+    /// Like [`FilePlayer::start`], reading interleaved samples with `read` until it returns 0.
+    pub fn start_with(
+        mut read: impl FnMut(&mut [f32]) -> Result<usize, FileError> + Send + 'static,
+        channels: usize,
+        sample_rate: u32,
+        samples: rtrb::Producer<f32>,
+        mode: FileMode,
+        diagnostics: Arc<StreamDiagnostics>,
+    ) -> Result<Self, FileError> {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (playback, mut target) = if mode == FileMode::Playback {
+            let (producer, consumer) =
+                rtrb::RingBuffer::new((PLAYBACK_SECONDS * sample_rate as f32) as usize * channels);
+            let stream = start_playback(consumer, channels, sample_rate, samples, diagnostics)?;
+            (Some(stream), producer)
+        } else {
+            (None, samples)
+        };
+
+        let feeder = std::thread::Builder::new()
+            .name("spexia-file".into())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    let start = Instant::now();
+                    let mut frames_pushed = 0u64;
+                    let mut buf = vec![0.0; BLOCK_FRAMES * channels];
+                    while !stop.load(Ordering::Relaxed) {
+                        if mode == FileMode::Realtime {
+                            let due = (start.elapsed().as_secs_f64() * sample_rate as f64) as u64;
+                            if frames_pushed >= due {
+                                std::thread::sleep(FEED_SLEEP);
+                                continue;
+                            }
+                        }
+                        let n = match read(&mut buf) {
+                            Ok(0) => break,
+                            Ok(n) => n,
+                            Err(err) => {
+                                eprintln!("{}", err);
+                                break;
+                            }
+                        };
+                        push_all(&mut target, &buf[..n], &stop);
+                        frames_pushed += (n / channels) as u64;
+                    }
+                    println!("end of input");
+                }
+            })
+            .expect("failed to spawn file thread");
+
+        Ok(Self {
+            stop,
+            feeder: Some(feeder),
+            playback,
+        })
+    }
+}
+impl Drop for FilePlayer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(feeder) = self.feeder.take() {
+            let _ = feeder.join();
+        }
+    }
+}
+
+/// Push all of `data`, waiting for room instead of dropping samples.
+fn push_all(samples: &mut rtrb::Producer<f32>, mut data: &[f32], stop: &AtomicBool) {
+    while !data.is_empty() && !stop.load(Ordering::Relaxed) {
+        let n = samples.slots().min(data.len());
+        if n == 0 {
+            std::thread::sleep(FEED_SLEEP);
+            continue;
+        }
+        let chunk = samples.write_chunk_uninit(n).unwrap();
+        chunk.fill_from_iter(data[..n].iter().copied());
+        data = &data[n..];
+    }
+}
+
+/// Play `playback` on the default output device, pushing every played sample on to the
+/// analysis through `samples`.
+fn start_playback(
+    playback: rtrb::Consumer<f32>,
+    channels: usize,
+    sample_rate: u32,
+    samples: rtrb::Producer<f32>,
+    diagnostics: Arc<StreamDiagnostics>,
+) -> Result<Stream, FileError> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| FileError::Playback("no output device".into()))?;
+    let config = device
+        .supported_output_configs()
+        .map_err(|err| FileError::Playback(err.to_string()))?
+        .filter(|it| capture::is_supported(it.sample_format()))
+        .filter_map(|it| it.try_with_sample_rate(sample_rate))
+        // prefer the file's channel count, then f32
+        .max_by_key(|it| {
+            (
+                it.channels() as usize == channels,
+                it.sample_format() == SampleFormat::F32,
+            )
+        })
+        .ok_or_else(|| {
+            FileError::Playback(format!("{} does not support {} Hz", device, sample_rate))
+        })?;
+    println!(
+        "playback on {} ({} channels, {} Hz, {})",
+        device,
+        config.channels(),
+        config.sample_rate(),
+        config.sample_format()
+    );
+
+    let player = Playback {
+        playback,
+        channels,
+        samples: Some(samples),
+        diagnostics,
+    };
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => build_playback::<i8>(&device, &config, player),
+        SampleFormat::I16 => build_playback::<i16>(&device, &config, player),
+        SampleFormat::I24 => build_playback::<cpal::I24>(&device, &config, player),
+        SampleFormat::I32 => build_playback::<i32>(&device, &config, player),
+        SampleFormat::I64 => build_playback::<i64>(&device, &config, player),
+        SampleFormat::U8 => build_playback::<u8>(&device, &config, player),
+        SampleFormat::U16 => build_playback::<u16>(&device, &config, player),
+        SampleFormat::U24 => build_playback::<cpal::U24>(&device, &config, player),
+        SampleFormat::U32 => build_playback::<u32>(&device, &config, player),
+        SampleFormat::U64 => build_playback::<u64>(&device, &config, player),
+        SampleFormat::F32 => build_playback::<f32>(&device, &config, player),
+        SampleFormat::F64 => build_playback::<f64>(&device, &config, player),
+        format => unreachable!("{} is filtered out above", format),
+    }
+    .map_err(|err| FileError::Playback(err.to_string()))?;
+    stream
+        .play()
+        .map_err(|err| FileError::Playback(err.to_string()))?;
+    Ok(stream)
+}
+
+/// State of the playback callback.
+struct Playback {
+    /// Decoded file samples waiting to be played.
+    playback: rtrb::Consumer<f32>,
+    channels: usize,
+    /// Where played samples go for analysis; dropped after the last one.
+    samples: Option<rtrb::Producer<f32>>,
+    diagnostics: Arc<StreamDiagnostics>,
+}
+impl Playback {
+    /// Fill `data` (interleaved, `out_channels` wide) from the file, repeating file channels
+    /// if the device has more, and silence once the file runs dry.
+    fn fill<T>(&mut self, data: &mut [T], out_channels: usize)
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let frames = (data.len() / out_channels).min(self.playback.slots() / self.channels);
+        if frames > 0 {
+            let chunk = self.playback.read_chunk(frames * self.channels).unwrap();
+            let (first, second) = chunk.as_slices();
+            let sample = |i: usize| {
+                if i < first.len() {
+                    first[i]
+                } else {
+                    second[i - first.len()]
+                }
+            };
+            for (f, frame) in data.chunks_exact_mut(out_channels).take(frames).enumerate() {
+                for (c, out) in frame.iter_mut().enumerate() {
+                    *out = T::from_sample(sample(f * self.channels + c % self.channels));
+                }
+            }
+            if let Some(samples) = &mut self.samples {
+                worker::push_samples(samples, first, &self.diagnostics);
+                worker::push_samples(samples, second, &self.diagnostics);
+            }
+            chunk.commit_all();
+        }
+        data[frames * out_channels..].fill(T::EQUILIBRIUM);
+        if self.playback.is_abandoned() && self.playback.is_empty() {
+            self.samples = None;
+        }
+    }
+}
+
+fn build_playback<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    mut player: Playback,
+) -> Result<Stream, cpal::Error>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let out_channels = config.channels() as usize;
+    device.build_output_stream(
+        config.config(),
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| player.fill(data, out_channels),
+        |err| eprintln!("an error occurred on the playback stream: {}", err),
+        None,
+    )
+}
@@ -0,0 +1,388 @@
+use std::{f64::consts::TAU, fmt::Display, str::FromStr};
+
+/// How long a chirp takes to sweep its range before starting over, unless given.
+const DEFAULT_CHIRP_SECONDS: f32 = 5.0;
+
+/// A synthetic test signal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    /// A sine at the given frequency in Hz.
+    Sine(f32),
+    /// Sines at several frequencies, mixed at equal levels.
+    Tones(Vec<f32>),
+    /// A naive (not band-limited) square wave.
+    Square(f32),
+    /// A sweep from `from` to `to` Hz over `seconds`, repeated. `log` sweeps at a constant
+    /// rate in octaves rather than in Hz.
+    Chirp {
+        from: f32,
+        to: f32,
+        seconds: f32,
+        log: bool,
+    },
+    WhiteNoise,
+    /// Noise with equal power per octave.
+    PinkNoise,
+    /// Single-sample clicks at the given rate per second.
+    Impulses(f32),
+}
+
+#[derive(Debug)]
+pub struct ParseSignalError(String);
+impl Display for ParseSignalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid signal {:?}, expected sine:<hz>, tones:<hz>,<hz>..., square:<hz>, \
+             chirp:<hz>-<hz>[:<seconds>], logchirp:<hz>-<hz>[:<seconds>], white, pink or \
+             impulses:<per second>",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseSignalError {}
+
+impl FromStr for Signal {
+    type Err = ParseSignalError;
+    /// The kind of signal, then its frequencies after a colon, e.g. `sine:440`,
+    /// `tones:440,660`, `logchirp:20-20000:10` or `pink`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseSignalError(s.to_string());
+        let hz = |value: &str| match value.trim().parse::<f32>() {
+            Ok(hz) if hz > 0.0 => Ok(hz),
+            _ => Err(err()),
+        };
+        let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+        let signal = match kind {
+            "sine" => Self::Sine(hz(params)?),
+            "tones" => Self::Tones(params.split(',').map(hz).collect::<Result<_, _>>()?),
+            "square" => Self::Square(hz(params)?),
+            "chirp" | "logchirp" => {
+                let (range, seconds) = match params.split_once(':') {
+                    Some((range, seconds)) => (range, hz(seconds)?),
+                    None => (params, DEFAULT_CHIRP_SECONDS),
+                };
+                let (from, to) = range.split_once('-').ok_or_else(err)?;
+                Self::Chirp {
+                    from: hz(from)?,
+                    to: hz(to)?,
+                    seconds,
+                    log: kind == "logchirp",
+                }
+            }
+            "white" if params.is_empty() => Self::WhiteNoise,
+            "pink" if params.is_empty() => Self::PinkNoise,
+            "impulses" => Self::Impulses(hz(params)?),
+            _ => return Err(err()),
+        };
+        Ok(signal)
+    }
+}
+impl Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sine(hz) => write!(f, "sine:{}", hz),
+            Self::Tones(hzs) => {
+                let hzs: Vec<String> = hzs.iter().map(|hz| hz.to_string()).collect();
+                write!(f, "tones:{}", hzs.join(","))
+            }
+            Self::Square(hz) => write!(f, "square:{}", hz),
+            Self::Chirp {
+                from,
+                to,
+                seconds,
+                log,
+            } => {
+                let kind = if *log { "logchirp" } else { "chirp" };
+                write!(f, "{}:{}-{}:{}", kind, from, to, seconds)
+            }
+            Self::WhiteNoise => write!(f, "white"),
+            Self::PinkNoise => write!(f, "pink"),
+            Self::Impulses(rate) => write!(f, "impulses:{}", rate),
+        }
+    }
+}
+
+/// Produces a [`Signal`] as mono f32 samples, like an [`AudioFile`](super::AudioFile) that
+/// never ends unless given a duration.
+pub struct SignalGenerator {
+    pub signal: Signal,
+    pub sample_rate: u32,
+    /// Peak level in dBFS.
+    pub level: f32,
+    amplitude: f32,
+    /// Frames left to produce, if limited.
+    remaining: Option<u64>,
+    /// Frames produced so far.
+    position: u64,
+    /// Oscillator phases in cycles, one per tone.
+    phases: Vec<f64>,
+    noise: Noise,
+}
+impl SignalGenerator {
+    pub const CHANNELS: usize = 1;
+
+    pub fn new(signal: Signal, level: f32, sample_rate: u32) -> Self {
+        let oscillators = match &signal {
+            Signal::Tones(hzs) => hzs.len(),
+            _ => 1,
+        };
+        // impulses start with a click
+        let phase = if matches!(signal, Signal::Impulses(_)) {
+            1.0
+        } else {
+            0.0
+        };
+        Self {
+            signal,
+            sample_rate,
+            level,
+            amplitude: 10f32.powf(level / 20.0),
+            remaining: None,
+            position: 0,
+            phases: vec![phase; oscillators],
+            noise: Noise::default(),
+        }
+    }
+    /// Stop after `seconds`.
+    pub fn with_duration(mut self, seconds: f32) -> Self {
+        self.remaining = Some(self.frames_in(seconds));
+        self
+    }
+    /// Total length in frames, if limited.
+    pub fn frames(&self) -> Option<u64> {
+        self.remaining.map(|remaining| self.position + remaining)
+    }
+    fn frames_in(&self, seconds: f32) -> u64 {
+        (seconds as f64 * self.sample_rate as f64).round() as u64
+    }
+
+    /// Fill `out` with samples. Returns how many were written; 0 means the end.
+    pub fn read(&mut self, out: &mut [f32]) -> usize {
+        let n = match self.remaining {
+            Some(remaining) => out.len().min(remaining as usize),
+            None => out.len(),
+        };
+        for out in &mut out[..n] {
+            *out = self.next_sample() * self.amplitude;
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= n as u64;
+        }
+        n
+    }
+
+    /// The next sample at full scale.
+    fn next_sample(&mut self) -> f32 {
+        let sample_rate = self.sample_rate as f64;
+        let t = self.position as f64 / sample_rate;
+        self.position += 1;
+        match &self.signal {
+            Signal::Sine(hz) => oscillate(&mut self.phases[0], *hz as f64 / sample_rate).sin(),
+            Signal::Tones(hzs) => {
+                let sum: f64 = hzs
+                    .iter()
+                    .zip(&mut self.phases)
+                    .map(|(hz, phase)| oscillate(phase, *hz as f64 / sample_rate).sin())
+                    .sum();
+                sum / hzs.len() as f64
+            }
+            Signal::Square(hz) => {
+                let phase = oscillate(&mut self.phases[0], *hz as f64 / sample_rate);
+                if phase.sin() >= 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Signal::Chirp {
+                from,
+                to,
+                seconds,
+                log,
+            } => {
+                let progress = (t / *seconds as f64).fract();
+                let (from, to) = (*from as f64, *to as f64);
+                let hz = if *log {
+                    from * (to / from).powf(progress)
+                } else {
+                    from + (to - from) * progress
+                };
+                oscillate(&mut self.phases[0], hz / sample_rate).sin()
+            }
+            Signal::WhiteNoise => self.noise.white() as f64,
+            Signal::PinkNoise => self.noise.pink() as f64,
+            Signal::Impulses(rate) => {
+                let phase = &mut self.phases[0];
+                let click = *phase >= 1.0;
+                if click {
+                    *phase -= 1.0;
+                }
+                *phase += *rate as f64 / sample_rate;
+                if click {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+        .clamp(-1.0, 1.0) as f32
+    }
+}
+
+/// Advance `phase` (in cycles) by `step`, returning the phase before the step in radians.
+fn oscillate(phase: &mut f64, step: f64) -> f64 {
+    let radians = *phase * TAU;
+    *phase = (*phase + step).fract();
+    radians
+}
+
+/// A small xorshift generator, so test signals are the same on every run, plus the state of
+/// the pinking filter.
+struct Noise {
+    state: u64,
+    pink: [f32; 7],
+}
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            state: 0x9e37_79b9_7f4a_7c15,
+            pink: [0.0; 7],
+        }
+    }
+}
+impl Noise {
+    /// Uniform in -1..1.
+    fn white(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+    /// White noise through Paul Kellet's -3 dB/octave filter.
+    fn pink(&mut self) -> f32 {
+        let white = self.white();
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[..6].iter().sum::<f32>() + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustfft::{num_complex::Complex32, num_traits::Zero};
+
+    use super::*;
+    use crate::audio::{FftConfig, SpectrumScaling, StftAnalyzer, WindowFunction};
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FFT_SIZE: usize = 4096;
+
+    /// One analysed frame of a test signal: its samples, spectrum, and reassigned frequency
+    /// and time per bin.
+    struct Frame {
+        samples: Vec<f32>,
+        spectrum: Vec<Complex32>,
+        freq: Vec<f32>,
+        time: Vec<f32>,
+    }
+    impl Frame {
+        /// Analyse the frame starting `start` samples into `signal`, generated at 0 dBFS.
+        fn analyze(signal: Signal, window: WindowFunction, start: usize) -> Self {
+            let mut analyzer = StftAnalyzer::new(
+                FftConfig::new(FFT_SIZE, FFT_SIZE),
+                window,
+                SpectrumScaling::Amplitude,
+            );
+            let mut generator = SignalGenerator::new(signal, 0.0, SAMPLE_RATE);
+            let mut samples = vec![0.0; start + analyzer.frame_len()];
+            assert_eq!(generator.read(&mut samples), samples.len());
+            samples.drain(..start);
+
+            let mut frame = Self {
+                samples,
+                spectrum: vec![Complex32::zero(); FFT_SIZE],
+                freq: vec![0.0; FFT_SIZE / 2],
+                time: vec![0.0; FFT_SIZE / 2],
+            };
+            analyzer.analyze(
+                &frame.samples,
+                SAMPLE_RATE as f32,
+                &mut frame.spectrum,
+                &mut frame.freq,
+                &mut frame.time,
+            );
+            frame
+        }
+        /// The strongest bin below Nyquist.
+        fn peak(&self) -> usize {
+            (0..FFT_SIZE / 2)
+                .max_by(|&a, &b| self.spectrum[a].norm().total_cmp(&self.spectrum[b].norm()))
+                .unwrap()
+        }
+        /// Seconds from the start of the frame to its centre.
+        fn centre(&self) -> f32 {
+            (FFT_SIZE - 1) as f32 / 2.0 / SAMPLE_RATE as f32
+        }
+    }
+
+    fn bin_hz() -> f32 {
+        SAMPLE_RATE as f32 / FFT_SIZE as f32
+    }
+
+    #[test]
+    fn sine_reassigns_to_its_frequency() {
+        for hz in [100.0, 1000.0, 1234.5, 15000.0] {
+            let frame = Frame::analyze(Signal::Sine(hz), WindowFunction::Hann, 1000);
+            let error = (frame.freq[frame.peak()] - hz).abs() / bin_hz();
+            assert!(error < 0.01, "{hz} Hz: off by {error} bins");
+        }
+    }
+
+    #[test]
+    fn sine_reads_0_dbfs() {
+        for hz in [100.0, 1000.0, 1234.5, 15000.0] {
+            let frame = Frame::analyze(Signal::Sine(hz), WindowFunction::FlatTop, 1000);
+            let db = 20.0 * frame.spectrum[frame.peak()].norm().log10();
+            assert!(db.abs() < 0.05, "{hz} Hz: {db} dBFS");
+        }
+    }
+
+    #[test]
+    fn chirp_reassigns_to_its_instantaneous_frequency() {
+        let (from, to, seconds) = (200.0, 8000.0, 1.0);
+        let chirp = Signal::Chirp {
+            from,
+            to,
+            seconds,
+            log: false,
+        };
+        let start = SAMPLE_RATE as usize / 2;
+        let frame = Frame::analyze(chirp, WindowFunction::Hann, start);
+        let peak = frame.peak();
+        // where the sweep is at the point's reassigned time
+        let t = start as f32 / SAMPLE_RATE as f32 + frame.centre() + frame.time[peak];
+        let expected = from + (to - from) * t / seconds;
+        let error = (frame.freq[peak] - expected).abs() / bin_hz();
+        assert!(error < 0.1, "{expected} Hz: off by {error} bins");
+    }
+
+    #[test]
+    fn impulse_reassigns_to_its_time() {
+        // one click every 5000 samples, the second landing 1000 samples into the frame
+        let frame = Frame::analyze(Signal::Impulses(9.6), WindowFunction::Hann, 4000);
+        let click = frame.samples.iter().position(|&it| it > 0.5).unwrap();
+        let expected = click as f32 / SAMPLE_RATE as f32 - frame.centre();
+        for bin in [frame.peak(), 10, 100, 1000] {
+            let error = (frame.time[bin] - expected).abs() * SAMPLE_RATE as f32;
+            assert!(error < 0.1, "bin {bin}: off by {error} samples");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use rustfft::num_complex::Complex32;
+
+use super::{
+    cqt::{ConstantQ, CqtConfig},
+    cwt::{CwtConfig, WaveletTransform},
+    multires::{MultiResConfig, MultiResolution},
+    synchrosqueeze::Synchrosqueezer,
+    zoom::{ZoomConfig, ZoomTransform},
+    FftConfig, WindowFunction,
+};
+
+/// Which analysis produces the spectrogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnalysisMode {
+    /// STFT bins drawn as points at their reassigned frequency and time.
+    #[default]
+    Reassigned,
+    /// STFT bins moved to the bin of their reassigned frequency, drawn as a grid.
+    Synchrosqueezed,
+    /// Constant-Q (or variable-Q) transform: logarithmically spaced bins, each with a window
+    /// a fixed number of its periods long.
+    ConstantQ,
+    /// STFTs of several sizes, the longest for the lowest frequencies.
+    MultiResolution,
+    /// Continuous wavelet transform on logarithmically spaced scales.
+    Wavelet,
+    /// Chirp-Z transform of a narrow band, stretched over the full height.
+    Zoom,
+}
+impl AnalysisMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reassigned => "reassigned",
+            Self::Synchrosqueezed => "synchrosqueezed",
+            Self::ConstantQ => "cqt",
+            Self::MultiResolution => "multires",
+            Self::Wavelet => "cwt",
+            Self::Zoom => "zoom",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Reassigned => Self::Synchrosqueezed,
+            Self::Synchrosqueezed => Self::ConstantQ,
+            Self::ConstantQ => Self::MultiResolution,
+            Self::MultiResolution => Self::Wavelet,
+            Self::Wavelet => Self::Zoom,
+            Self::Zoom => Self::Reassigned,
+        }
+    }
+    /// Whether chunks carry a [`SpectrumGrid`] to draw, rather than only STFT points.
+    pub fn is_grid(&self) -> bool {
+        *self != Self::Reassigned
+    }
+    /// Parse a [`AnalysisMode::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Reassigned,
+            Self::Synchrosqueezed,
+            Self::ConstantQ,
+            Self::MultiResolution,
+            Self::Wavelet,
+            Self::Zoom,
+        ]
+        .into_iter()
+        .find(|mode| mode.name() == name)
+    }
+}
+
+/// One hop of a grid-based analysis: a magnitude per frequency row, as opposed to the
+/// scattered points of the reassigned spectrogram.
+#[derive(Debug, Clone)]
+pub struct SpectrumGrid {
+    /// Centre frequency in Hz of each row, ascending.
+    pub freqs: Vec<f32>,
+    /// Magnitude of each row per channel, normalized like [`AudioDataChunk::spectrum`].
+    ///
+    /// [`AudioDataChunk::spectrum`]: super::AudioDataChunk::spectrum
+    pub magnitude: Vec<Vec<f32>>,
+    /// Frequency range in Hz to show linearly over the full height, instead of the usual
+    /// logarithmic display.
+    pub linear_range: Option<(f32, f32)>,
+}
+
+/// Settings of the grid-based analyses that the STFT's [`FftConfig`] does not cover.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GridConfig {
+    pub cqt: CqtConfig,
+    pub multires: MultiResConfig,
+    pub cwt: CwtConfig,
+    pub zoom: ZoomConfig,
+}
+
+/// Per-channel state of the grid-based analysis selected by an [`AnalysisMode`].
+#[derive(Clone)]
+pub enum GridAnalyzer {
+    /// The mode draws STFT points, not a grid.
+    None,
+    Synchrosqueeze(Synchrosqueezer),
+    ConstantQ(ConstantQ),
+    MultiResolution(MultiResolution),
+    Wavelet(WaveletTransform),
+    Zoom(ZoomTransform),
+}
+impl GridAnalyzer {
+    pub fn new(
+        mode: AnalysisMode,
+        fft_config: FftConfig,
+        window: WindowFunction,
+        grid_config: &GridConfig,
+        sample_rate: f32,
+    ) -> Self {
+        match mode {
+            AnalysisMode::Reassigned => Self::None,
+            AnalysisMode::Synchrosqueezed => {
+                Self::Synchrosqueeze(Synchrosqueezer::new(fft_config, window))
+            }
+            AnalysisMode::ConstantQ => {
+                Self::ConstantQ(ConstantQ::new(grid_config.cqt, window, sample_rate))
+            }
+            AnalysisMode::MultiResolution => Self::MultiResolution(MultiResolution::new(
+                &grid_config.multires,
+                window,
+                sample_rate,
+            )),
+            AnalysisMode::Wavelet => {
+                Self::Wavelet(WaveletTransform::new(grid_config.cwt, sample_rate))
+            }
+            AnalysisMode::Zoom => {
+                Self::Zoom(ZoomTransform::new(grid_config.zoom, window, sample_rate))
+            }
+        }
+    }
+    /// Centre frequency in Hz of each row, or `None` if the mode has no grid.
+    pub fn freqs(&self, sample_rate: f32) -> Option<Vec<f32>> {
+        match self {
+            Self::None => None,
+            Self::Synchrosqueeze(squeezer) => Some(squeezer.freqs(sample_rate)),
+            Self::ConstantQ(cqt) => Some(cqt.freqs()),
+            Self::MultiResolution(multires) => Some(multires.freqs()),
+            Self::Wavelet(cwt) => Some(cwt.freqs()),
+            Self::Zoom(zoom) => Some(zoom.freqs()),
+        }
+    }
+    /// See [`SpectrumGrid::linear_range`].
+    pub fn linear_range(&self) -> Option<(f32, f32)> {
+        match self {
+            Self::Zoom(zoom) => Some(zoom.band()),
+            _ => None,
+        }
+    }
+    /// Samples the analysis needs per frame, or 0 if the STFT's frame is enough.
+    pub fn frame_len(&self) -> usize {
+        match self {
+            Self::None | Self::Synchrosqueeze(_) => 0,
+            Self::ConstantQ(cqt) => cqt.frame_len(),
+            Self::MultiResolution(multires) => multires.frame_len(),
+            Self::Wavelet(cwt) => cwt.frame_len(),
+            Self::Zoom(zoom) => zoom.frame_len(),
+        }
+    }
+    /// Analyse one hop from its frame (at least [`Self::frame_len`] samples, ending with the
+    /// newest) or from the STFT of the frame's last `fft_config.size + 1` samples, writing one
+    /// magnitude per row.
+    pub fn analyze(
+        &mut self,
+        frame: &[f32],
+        spectrum: &[Complex32],
+        freq: &[f32],
+        sample_rate: f32,
+        magnitude: &mut Vec<f32>,
+    ) {
+        match self {
+            Self::None => {}
+            Self::Synchrosqueeze(squeezer) => {
+                magnitude.resize(freq.len(), 0.0);
+                squeezer.analyze(spectrum, freq, sample_rate, magnitude);
+            }
+            Self::ConstantQ(cqt) => {
+                magnitude.resize(cqt.bins(), 0.0);
+                cqt.analyze(frame, magnitude);
+            }
+            Self::MultiResolution(multires) => {
+                magnitude.resize(multires.rows(), 0.0);
+                multires.analyze(frame, magnitude);
+            }
+            Self::Wavelet(cwt) => {
+                magnitude.resize(cwt.scales(), 0.0);
+                cwt.analyze(frame, magnitude);
+            }
+            Self::Zoom(zoom) => {
+                magnitude.resize(zoom.rows(), 0.0);
+                zoom.analyze(frame, magnitude);
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt::Display, ops::Range, str::FromStr, sync::Arc};
+
+use rayon::prelude::*;
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+
+use super::{FftConfig, WindowFunction};
+
+/// FFT sizes of the multi-resolution spectrogram, from the lowest band up, and the crossover
+/// frequencies between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiResConfig {
+    /// One FFT size per band, each a power of two within the [`FftConfig`] limits.
+    pub sizes: Vec<usize>,
+    /// One fewer than `sizes`, ascending: band `i` covers `crossovers[i - 1]` to
+    /// `crossovers[i]` Hz.
+    pub crossovers: Vec<f32>,
+}
+impl Default for MultiResConfig {
+    fn default() -> Self {
+        Self {
+            sizes: vec![8192, 2048, 512],
+            crossovers: vec![250.0, 2000.0],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseMultiResConfigError(String);
+impl Display for ParseMultiResConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid multi-resolution bands {:?}, expected FFT sizes from the lowest band up \
+             separated by ascending crossover frequencies, like \"8192,250,2048,2000,512\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseMultiResConfigError {}
+
+impl FromStr for MultiResConfig {
+    type Err = ParseMultiResConfigError;
+    /// `size,hz,size,...,size`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMultiResConfigError(s.to_string());
+        let mut config = Self {
+            sizes: vec![],
+            crossovers: vec![],
+        };
+        for (i, part) in s.split(',').map(str::trim).enumerate() {
+            if i % 2 == 0 {
+                let size: usize = part.parse().map_err(|_| err())?;
+                if !size.is_power_of_two()
+                    || !(FftConfig::MIN_SIZE..=FftConfig::MAX_SIZE).contains(&size)
+                {
+                    return Err(err());
+                }
+                config.sizes.push(size);
+            } else {
+                let freq: f32 = part.parse().map_err(|_| err())?;
+                if freq <= config.crossovers.last().copied().unwrap_or(0.0) {
+                    return Err(err());
+                }
+                config.crossovers.push(freq);
+            }
+        }
+        // ends with a size
+        if config.sizes.len() != config.crossovers.len() + 1 {
+            return Err(err());
+        }
+        Ok(config)
+    }
+}
+impl Display for MultiResConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, size) in self.sizes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",{},", self.crossovers[i - 1])?;
+            }
+            write!(f, "{size}")?;
+        }
+        Ok(())
+    }
+}
+
+/// One FFT size and the bins of it shown.
+#[derive(Clone)]
+struct Band {
+    fft: Arc<dyn Fft<f32>>,
+    /// Window coefficients scaled so a full scale sinusoid gives 1.
+    window: Arc<Vec<f32>>,
+    bins: Range<usize>,
+    buf: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+impl Band {
+    fn analyze(&mut self, frame: &[f32], magnitude: &mut [f32]) {
+        let frame = &frame[frame.len() - self.buf.len()..];
+        for ((buf, &sample), w) in self.buf.iter_mut().zip(frame).zip(self.window.iter()) {
+            *buf = Complex32::new(sample * w, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        for (magnitude, value) in magnitude.iter_mut().zip(&self.buf[self.bins.clone()]) {
+            *magnitude = value.norm();
+        }
+    }
+}
+
+/// Several STFTs of different sizes over the same hops, composited by frequency: long frames
+/// resolve the low end, short ones keep the timing of the high end.
+///
+/// Like the constant-Q transform, every band's frame ends at the newest sample, so the short
+/// frames are not delayed to line up with the long ones. Bands are analysed in parallel.
+#[derive(Clone)]
+pub struct MultiResolution {
+    bands: Vec<Band>,
+    freqs: Arc<Vec<f32>>,
+}
+impl MultiResolution {
+    pub fn new(config: &MultiResConfig, window: WindowFunction, sample_rate: f32) -> Self {
+        let mut planner = FftPlanner::new();
+        let mut bands = vec![];
+        let mut freqs = vec![];
+        for (i, &size) in config.sizes.iter().enumerate() {
+            let bin_hz = sample_rate / size as f32;
+            let first_bin = |freq: f32| ((freq / bin_hz).ceil() as usize).min(size / 2);
+            let start = i
+                .checked_sub(1)
+                .map_or(0, |i| first_bin(config.crossovers[i]));
+            let end = config
+                .crossovers
+                .get(i)
+                .map_or(size / 2, |&freq| first_bin(freq))
+                .max(start);
+            freqs.extend((start..end).map(|k| k as f32 * bin_hz));
+
+            let fft = planner.plan_fft_forward(size);
+            let mut coefficients = window.coefficients(size);
+            let scale = 2.0 / coefficients.iter().sum::<f32>();
+            coefficients.iter_mut().for_each(|w| *w *= scale);
+            bands.push(Band {
+                scratch: vec![Complex32::zero(); fft.get_inplace_scratch_len()],
+                buf: vec![Complex32::zero(); size],
+                fft,
+                window: Arc::new(coefficients),
+                bins: start..end,
+            });
+        }
+        Self {
+            bands,
+            freqs: Arc::new(freqs),
+        }
+    }
+    /// Samples needed per frame: the largest FFT size.
+    pub fn frame_len(&self) -> usize {
+        self.bands.iter().map(|it| it.buf.len()).max().unwrap_or(0)
+    }
+    /// Number of output rows, over all bands.
+    pub fn rows(&self) -> usize {
+        self.freqs.len()
+    }
+    /// Centre frequency in Hz of each row, ascending.
+    pub fn freqs(&self) -> Vec<f32> {
+        self.freqs.to_vec()
+    }
+    /// Analyse the newest samples of `frame` with every band into its rows of `magnitude`.
+    pub fn analyze(&mut self, frame: &[f32], magnitude: &mut [f32]) {
+        let mut rows = vec![];
+        let mut rest = magnitude;
+        for band in &self.bands {
+            let (band_rows, next) = rest.split_at_mut(band.bins.len());
+            rows.push(band_rows);
+            rest = next;
+        }
+        self.bands
+            .par_iter_mut()
+            .zip(rows)
+            .for_each(|(band, rows)| band.analyze(frame, rows));
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+
+/// Highest fundamental looked for, in Hz. The lowest is set by the frame: a period has to fit
+/// in half of it.
+const MAX_FREQ: f32 = 4000.0;
+/// YIN's absolute threshold: the first period whose normalized difference dips below this
+/// is taken, rather than the global minimum, to avoid picking multiples of the period.
+const THRESHOLD: f32 = 0.15;
+/// Frames with less mean power than this (about -90 dBFS) are silent, not unvoiced.
+const MIN_POWER: f32 = 1e-9;
+
+/// A fundamental frequency estimate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pitch {
+    /// Fundamental in Hz.
+    pub freq: f32,
+    /// How periodic the frame is at that frequency, from 0 (noise) to 1 (exactly periodic).
+    pub confidence: f32,
+}
+
+/// YIN fundamental frequency estimator for frames of one size.
+///
+/// The difference function is worked out from an FFT cross-correlation and running energy
+/// sums, so a frame costs three FFTs rather than a multiply per sample and lag.
+pub struct PitchDetector {
+    size: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    /// The first half of the frame, zero padded.
+    buf_head: Vec<Complex32>,
+    buf: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+    /// Cumulative energy of the frame, `energy[i]` being the sum of the first `i` squares.
+    energy: Vec<f32>,
+    /// Cumulative mean normalized difference per lag.
+    difference: Vec<f32>,
+}
+impl PitchDetector {
+    pub fn new(size: usize) -> Self {
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(size);
+        let ifft = planner.plan_fft_inverse(size);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+        Self {
+            size,
+            fft,
+            ifft,
+            buf_head: vec![Complex32::zero(); size],
+            buf: vec![Complex32::zero(); size],
+            scratch: vec![Complex32::zero(); scratch_len],
+            energy: vec![0.0; size + 1],
+            difference: vec![0.0; size / 2],
+        }
+    }
+
+    /// Estimate the fundamental of `frame` (`size` samples), or `None` if it is silent or
+    /// nothing in range repeats at all.
+    pub fn estimate(&mut self, frame: &[f32], sample_rate: f32) -> Option<Pitch> {
+        let size = self.size;
+        let window = size / 2;
+        assert_eq!(frame.len(), size);
+
+        for (i, &sample) in frame.iter().enumerate() {
+            self.energy[i + 1] = self.energy[i] + sample * sample;
+        }
+        if self.energy[window] < MIN_POWER * window as f32 {
+            return None;
+        }
+
+        // r(τ) = Σ x[j] x[j + τ] over the first half of the frame, for every lag below half
+        // the frame, as a circular correlation that never wraps
+        for (i, &sample) in frame.iter().enumerate() {
+            self.buf[i] = Complex32::new(sample, 0.0);
+            self.buf_head[i] = Complex32::new(if i < window { sample } else { 0.0 }, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        self.fft
+            .process_with_scratch(&mut self.buf_head, &mut self.scratch);
+        for (x, head) in self.buf.iter_mut().zip(&self.buf_head) {
+            *x *= head.conj() / size as f32;
+        }
+        self.ifft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+
+        // d(τ) = Σ (x[j] - x[j + τ])², normalized by its mean over the smaller lags
+        let head_energy = self.energy[window];
+        let mut sum = 0.0;
+        self.difference[0] = 1.0;
+        for tau in 1..window {
+            let lag_energy = self.energy[tau + window] - self.energy[tau];
+            let d = (head_energy + lag_energy - 2.0 * self.buf[tau].re).max(0.0);
+            sum += d;
+            self.difference[tau] = if sum > 0.0 { d * tau as f32 / sum } else { 1.0 };
+        }
+
+        let min_tau = ((sample_rate / MAX_FREQ) as usize).max(2);
+        let lags = min_tau..window - 1;
+        let below = lags.clone().find(|&tau| self.difference[tau] < THRESHOLD);
+        let tau = match below {
+            // walk down to the bottom of the dip
+            Some(mut tau) => {
+                while tau + 1 < window - 1 && self.difference[tau + 1] < self.difference[tau] {
+                    tau += 1;
+                }
+                tau
+            }
+            None => lags.min_by(|&a, &b| self.difference[a].total_cmp(&self.difference[b]))?,
+        };
+
+        // parabolic interpolation between the neighbouring lags
+        let (a, b, c) = (
+            self.difference[tau - 1],
+            self.difference[tau],
+            self.difference[tau + 1],
+        );
+        let curvature = a - 2.0 * b + c;
+        let offset = if curvature > 0.0 {
+            ((a - c) / (2.0 * curvature)).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        Some(Pitch {
+            freq: sample_rate / (tau as f32 + offset),
+            confidence: (1.0 - b).clamp(0.0, 1.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Estimate the pitch of a frame of `waveform`, given the phase from 0 to 1, repeating
+    /// every `period` samples.
+    fn estimate(period: f32, waveform: impl Fn(f32) -> f32) -> Option<Pitch> {
+        let frame: Vec<f32> = (0..4096)
+            .map(|i| waveform((i as f32 / period).fract()))
+            .collect();
+        PitchDetector::new(frame.len()).estimate(&frame, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn finds_period_of_sine() {
+        for period in [24.0, 100.0, 333.3, 1000.0] {
+            let pitch = estimate(period, |phase| (std::f32::consts::TAU * phase).sin()).unwrap();
+            let expected = SAMPLE_RATE / period;
+            let error = (pitch.freq / expected - 1.0).abs();
+            assert!(error < 1e-3, "period {period}: {} Hz", pitch.freq);
+            assert!(pitch.confidence > 0.9, "period {period}: {pitch:?}");
+        }
+    }
+
+    #[test]
+    fn finds_fundamental_rather_than_harmonics() {
+        // a sawtooth, and a narrow pulse train whose first harmonics are nearly as strong as
+        // its fundamental
+        for waveform in [
+            |phase: f32| 2.0 * phase - 1.0,
+            |phase: f32| (phase < 0.1) as u8 as f32,
+        ] {
+            let pitch = estimate(218.5, waveform).unwrap();
+            let expected = SAMPLE_RATE / 218.5;
+            let error = (pitch.freq / expected - 1.0).abs();
+            assert!(error < 1e-3, "{} Hz", pitch.freq);
+        }
+    }
+
+    #[test]
+    fn silence_has_no_pitch() {
+        assert_eq!(estimate(100.0, |_| 0.0), None);
+    }
+}
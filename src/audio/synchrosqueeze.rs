@@ -0,0 +1,101 @@
+use rustfft::{num_complex::Complex32, num_traits::Zero};
+
+use super::{FftConfig, WindowFunction};
+
+/// Bins quieter than this (about -140 dBFS) are not moved, their frequency estimate being
+/// meaningless.
+const MIN_MAGNITUDE: f32 = 1e-7;
+
+/// Synchrosqueezed STFT: every bin's complex value is moved to the bin of its reassigned
+/// frequency, sharpening ridges while staying on the FFT's bin grid. Summing the squeezed
+/// bins still gives back the frame's centre sample, so the transform remains invertible.
+#[derive(Clone)]
+pub struct Synchrosqueezer {
+    fft_config: FftConfig,
+    /// Brings a squeezed sinusoid back to the level of its plain STFT peak.
+    gain: f32,
+    squeezed: Vec<Complex32>,
+}
+impl Synchrosqueezer {
+    pub fn new(fft_config: FftConfig, window: WindowFunction) -> Self {
+        let coefficients = window.coefficients(fft_config.size);
+        let sum: f32 = coefficients.iter().sum();
+        let centre = coefficients[fft_config.half_size()].max(f32::EPSILON);
+        Self {
+            fft_config,
+            gain: sum / (fft_config.size as f32 * centre),
+            squeezed: vec![Complex32::zero(); fft_config.half_size()],
+        }
+    }
+    /// Centre frequency in Hz of each output row.
+    pub fn freqs(&self, sample_rate: f32) -> Vec<f32> {
+        let bin_hz = sample_rate / self.fft_config.size as f32;
+        (0..self.fft_config.half_size())
+            .map(|k| k as f32 * bin_hz)
+            .collect()
+    }
+    /// Squeeze the lower half of `spectrum`, with reassigned frequencies `freq` in Hz, into
+    /// `magnitude`, one row per bin.
+    pub fn analyze(
+        &mut self,
+        spectrum: &[Complex32],
+        freq: &[f32],
+        sample_rate: f32,
+        magnitude: &mut [f32],
+    ) {
+        let bin_hz = sample_rate / self.fft_config.size as f32;
+        self.squeezed.fill(Complex32::zero());
+        for (k, (&value, &freq)) in spectrum.iter().zip(freq).enumerate() {
+            if value.norm() < MIN_MAGNITUDE {
+                continue;
+            }
+            let target = (freq / bin_hz).round() as usize;
+            if let Some(squeezed) = self.squeezed.get_mut(target) {
+                // the FFT's phase refers to the start of the frame; referred to its centre
+                // instead (a factor of (-1)^k for periodic windows), the bins of one component
+                // line up in phase and add up coherently
+                *squeezed += if k % 2 == 0 { value } else { -value };
+            }
+        }
+        for (magnitude, squeezed) in magnitude.iter_mut().zip(&self.squeezed) {
+            *magnitude = squeezed.norm() * self.gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{SpectrumScaling, StftAnalyzer};
+
+    /// Level in dBFS of the loudest squeezed row for a full scale sine at `hz`.
+    fn squeezed_peak_db(hz: f32, window: WindowFunction) -> f32 {
+        let sample_rate = 48000.0;
+        let fft_config = FftConfig::new(4096, 4096);
+        let mut analyzer = StftAnalyzer::new(fft_config, window, SpectrumScaling::Amplitude);
+        let mut squeezer = Synchrosqueezer::new(fft_config, window);
+        let frame: Vec<f32> = (0..analyzer.frame_len())
+            .map(|i| (std::f32::consts::TAU * hz * i as f32 / sample_rate).sin())
+            .collect();
+        let mut spectrum = vec![Complex32::zero(); fft_config.size];
+        let mut freq = vec![0.0; fft_config.half_size()];
+        let mut time = vec![0.0; fft_config.half_size()];
+        analyzer.analyze(&frame, sample_rate, &mut spectrum, &mut freq, &mut time);
+        let mut magnitude = vec![0.0; fft_config.half_size()];
+        squeezer.analyze(&spectrum, &freq, sample_rate, &mut magnitude);
+        20.0 * magnitude.iter().fold(0.0f32, |a, &b| a.max(b)).log10()
+    }
+
+    #[test]
+    fn squeezed_sine_reads_0_dbfs() {
+        // on a bin centre, and off it where the plain STFT's peak is lower. A sine exactly
+        // halfway between two bins is split between their rows.
+        let bin_hz = 48000.0 / 4096.0;
+        for hz in [85.0 * bin_hz, 85.25 * bin_hz, 85.45 * bin_hz] {
+            for window in [WindowFunction::Hann, WindowFunction::BlackmanHarris] {
+                let db = squeezed_peak_db(hz, window);
+                assert!(db.abs() < 0.01, "{hz} Hz, {}: {db} dBFS", window.name());
+            }
+        }
+    }
+}
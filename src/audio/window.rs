@@ -0,0 +1,211 @@
+use std::f64::consts::TAU;
+
+/// Analysis window applied to each frame before the FFT.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    /// 4-term, -92 dB sidelobes.
+    BlackmanHarris,
+    /// 5-term flat-top, scalloping loss below 0.01 dB, for level measurements.
+    FlatTop,
+    Kaiser {
+        beta: f32,
+    },
+    /// `sigma` is relative to half the frame length.
+    Gaussian {
+        sigma: f32,
+    },
+}
+impl WindowFunction {
+    const KAISER_BETA: f32 = 9.0;
+    const GAUSSIAN_SIGMA: f32 = 0.4;
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Rectangular => "rectangular".into(),
+            Self::Hann => "hann".into(),
+            Self::Hamming => "hamming".into(),
+            Self::BlackmanHarris => "blackman-harris".into(),
+            Self::FlatTop => "flat-top".into(),
+            Self::Kaiser { beta } => format!("kaiser(β={beta})"),
+            Self::Gaussian { sigma } => format!("gaussian(σ={sigma:.2})"),
+        }
+    }
+
+    /// The next window in the selection cycle, with default parameters.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Rectangular => Self::Hann,
+            Self::Hann => Self::Hamming,
+            Self::Hamming => Self::BlackmanHarris,
+            Self::BlackmanHarris => Self::FlatTop,
+            Self::FlatTop => Self::Kaiser {
+                beta: Self::KAISER_BETA,
+            },
+            Self::Kaiser { .. } => Self::Gaussian {
+                sigma: Self::GAUSSIAN_SIGMA,
+            },
+            Self::Gaussian { .. } => Self::Rectangular,
+        }
+    }
+
+    /// Step the shape parameter of parameterized windows (β for Kaiser, σ for Gaussian).
+    pub fn step_param(self, up: bool) -> Self {
+        let sign = if up { 1.0 } else { -1.0 };
+        match self {
+            Self::Kaiser { beta } => Self::Kaiser {
+                beta: (beta + sign).clamp(0.0, 40.0),
+            },
+            Self::Gaussian { sigma } => Self::Gaussian {
+                sigma: (sigma + 0.05 * sign).clamp(0.05, 1.0),
+            },
+            other => other,
+        }
+    }
+
+    /// Periodic (DFT-even) window coefficients for a frame of `n` samples.
+    pub fn coefficients(&self, n: usize) -> Vec<f32> {
+        let cosine_sum = |a: &[f64], i: usize| {
+            let phase = TAU * i as f64 / n as f64;
+            a.iter()
+                .enumerate()
+                .map(|(k, a_k)| {
+                    let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                    sign * a_k * (phase * k as f64).cos()
+                })
+                .sum::<f64>()
+        };
+        (0..n)
+            .map(|i| {
+                (match *self {
+                    Self::Rectangular => 1.0,
+                    Self::Hann => cosine_sum(&[0.5, 0.5], i),
+                    Self::Hamming => cosine_sum(&[0.54, 0.46], i),
+                    Self::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], i),
+                    Self::FlatTop => cosine_sum(
+                        &[
+                            0.21557895,
+                            0.41663158,
+                            0.277263158,
+                            0.083578947,
+                            0.006947368,
+                        ],
+                        i,
+                    ),
+                    Self::Kaiser { beta } => {
+                        let r = 2.0 * i as f64 / n as f64 - 1.0;
+                        bessel_i0(beta as f64 * (1.0 - r * r).sqrt()) / bessel_i0(beta as f64)
+                    }
+                    Self::Gaussian { sigma } => {
+                        let r = (2.0 * i as f64 / n as f64 - 1.0) / sigma as f64;
+                        (-0.5 * r * r).exp()
+                    }
+                }) as f32
+            })
+            .collect()
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind, by power series.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x_sq = 0.25 * x * x;
+    for k in 1..64 {
+        term *= half_x_sq / (k * k) as f64;
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// How spectrum magnitudes are normalized to compensate for the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrumScaling {
+    /// Coherent gain compensation: a full scale sinusoid reads 1.0 (0 dBFS) at its peak.
+    #[default]
+    Amplitude,
+    /// Additionally compensates the equivalent noise bandwidth, so broadband noise reads the
+    /// same level regardless of window.
+    Noise,
+}
+impl SpectrumScaling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Amplitude => "amplitude",
+            Self::Noise => "noise",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Amplitude => Self::Noise,
+            Self::Noise => Self::Amplitude,
+        }
+    }
+    /// Factor applied to raw FFT output of a frame windowed with `window`.
+    pub fn factor(&self, window: &[f32]) -> f32 {
+        let sum: f64 = window.iter().map(|&w| w as f64).sum();
+        let sum_sq: f64 = window.iter().map(|&w| (w * w) as f64).sum();
+        // One-sided spectrum: each real sinusoid splits its energy between two bins.
+        let amplitude = 2.0 / sum;
+        (match self {
+            Self::Amplitude => amplitude,
+            Self::Noise => {
+                let enbw = window.len() as f64 * sum_sq / (sum * sum);
+                amplitude / enbw.sqrt()
+            }
+        }) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level in dBFS of the strongest bin of a full scale sine `cycles` periods per frame
+    /// long, analysed under `window` with `scaling`.
+    fn peak_db(window: WindowFunction, scaling: SpectrumScaling, cycles: f64) -> f64 {
+        let n = 4096;
+        let coefficients = window.coefficients(n);
+        let factor = scaling.factor(&coefficients) as f64;
+        let bins = cycles.floor() as usize - 2..cycles.ceil() as usize + 3;
+        bins.map(|k| {
+            let (re, im) = coefficients
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (i, &w)| {
+                    let sample = (TAU * cycles * i as f64 / n as f64 + 0.3).sin() * w as f64;
+                    let phase = TAU * (k * i) as f64 / n as f64;
+                    (re + sample * phase.cos(), im - sample * phase.sin())
+                });
+            20.0 * (factor * re.hypot(im)).log10()
+        })
+        .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// Every window in the selection cycle, with default parameters.
+    fn all_windows() -> impl Iterator<Item = WindowFunction> {
+        std::iter::successors(Some(WindowFunction::Rectangular), |it| Some(it.next())).take(7)
+    }
+
+    #[test]
+    fn full_scale_sine_reads_0_dbfs_under_every_window() {
+        for window in all_windows() {
+            let db = peak_db(window, SpectrumScaling::Amplitude, 400.0);
+            assert!(db.abs() < 0.05, "{}: {db} dBFS", window.name());
+        }
+    }
+
+    #[test]
+    fn flat_top_reads_0_dbfs_between_bins() {
+        for cycles in [400.25, 400.5, 400.75] {
+            let db = peak_db(WindowFunction::FlatTop, SpectrumScaling::Amplitude, cycles);
+            assert!(db.abs() < 0.01, "{cycles} cycles: {db} dBFS");
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use cpal::{FromSample, Sample};
+
+use super::{
+    AnalysisMode, AudioDataChunk, ChannelMap, FftConfig, GridConfig, SpectrumScaling, StreamData,
+    WindowFunction,
+};
+
+/// Seconds of interleaved samples buffered between the capture callback and the analysis
+/// worker.
+const RING_SECONDS: f32 = 2.0;
+/// Analysed chunks buffered between the analysis worker and the render loop.
+pub const CHUNK_QUEUE_LEN: usize = 256;
+/// How long the worker sleeps when the ring buffer has no new samples.
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+pub enum AnalysisCommand {
+    /// Start reading from a new capture ring buffer (after the device changed).
+    NewInput {
+        samples: rtrb::Consumer<f32>,
+        sample_rate: f32,
+        channels: usize,
+        /// Wait for the render loop instead of dropping chunks when it falls behind.
+        lossless: bool,
+    },
+    FftConfig(FftConfig),
+    Window(WindowFunction, SpectrumScaling),
+    ChannelMap(ChannelMap),
+    Mode(AnalysisMode),
+    GridConfig(GridConfig),
+}
+
+/// Counters shared between the capture callback, the analysis worker and whoever wants to
+/// know why the display stuttered.
+#[derive(Default)]
+pub struct StreamDiagnostics {
+    /// Capture callbacks whose samples did not fit in the ring buffer.
+    pub overruns: AtomicU64,
+    /// Samples discarded by those callbacks.
+    pub dropped_samples: AtomicU64,
+    /// Analysed chunks discarded because the render loop fell behind.
+    pub dropped_chunks: AtomicU64,
+    /// Hops analysed since startup.
+    pub hops: AtomicU64,
+    /// Set once the producer of the current input is gone and all its samples are analysed,
+    /// e.g. at the end of a file.
+    pub input_ended: AtomicBool,
+}
+impl StreamDiagnostics {
+    pub fn snapshot(&self) -> DiagnosticsSnapshot {
+        DiagnosticsSnapshot {
+            overruns: self.overruns.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            dropped_chunks: self.dropped_chunks.load(Ordering::Relaxed),
+            hops: self.hops.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticsSnapshot {
+    pub overruns: u64,
+    pub dropped_samples: u64,
+    pub dropped_chunks: u64,
+    pub hops: u64,
+}
+impl Display for DiagnosticsSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "hops: {}, ring overruns: {} ({} samples dropped), chunks dropped: {}",
+            self.hops, self.overruns, self.dropped_samples, self.dropped_chunks
+        )
+    }
+}
+
+/// A ring buffer for capturing `channels` interleaved channels at `sample_rate`.
+pub fn ring_buffer(
+    sample_rate: f32,
+    channels: usize,
+) -> (rtrb::Producer<f32>, rtrb::Consumer<f32>) {
+    rtrb::RingBuffer::new((RING_SECONDS * sample_rate) as usize * channels)
+}
+
+/// Push one capture callback's worth of interleaved samples, converted to f32. Wait-free; if
+/// the ring buffer can't take the whole buffer it is dropped and counted as an overrun.
+pub fn push_samples<T>(
+    samples: &mut rtrb::Producer<f32>,
+    data: &[T],
+    diagnostics: &StreamDiagnostics,
+) where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    match samples.write_chunk_uninit(data.len()) {
+        Ok(chunk) => {
+            chunk.fill_from_iter(data.iter().map(|s| s.to_sample::<f32>()));
+        }
+        Err(_) => {
+            diagnostics.overruns.fetch_add(1, Ordering::Relaxed);
+            diagnostics
+                .dropped_samples
+                .fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Spawn the analysis worker thread. It runs until `commands` is disconnected.
+pub fn spawn(
+    fft_config: FftConfig,
+    commands: mpsc::Receiver<AnalysisCommand>,
+    chunks: mpsc::SyncSender<AudioDataChunk>,
+    diagnostics: Arc<StreamDiagnostics>,
+) -> JoinHandle<()> {
+    std::thread::Builder::new()
+        .name("spexia-analysis".into())
+        .spawn(move || {
+            let mut stream_data = StreamData::new(0.0, fft_config);
+            let mut samples: Option<rtrb::Consumer<f32>> = None;
+            let mut lossless = false;
+            loop {
+                loop {
+                    match commands.try_recv() {
+                        Ok(AnalysisCommand::NewInput {
+                            samples: new_samples,
+                            sample_rate,
+                            channels,
+                            lossless: new_lossless,
+                        }) => {
+                            samples = Some(new_samples);
+                            lossless = new_lossless;
+                            diagnostics.input_ended.store(false, Ordering::Relaxed);
+                            stream_data.reset(sample_rate, channels);
+                        }
+                        Ok(AnalysisCommand::FftConfig(fft_config)) => {
+                            stream_data.set_fft_config(fft_config);
+                        }
+                        Ok(AnalysisCommand::Window(window, scaling)) => {
+                            stream_data.set_window(window, scaling);
+                        }
+                        Ok(AnalysisCommand::ChannelMap(channel_map)) => {
+                            stream_data.set_channel_map(channel_map);
+                        }
+                        Ok(AnalysisCommand::Mode(mode)) => {
+                            stream_data.set_mode(mode);
+                        }
+                        Ok(AnalysisCommand::GridConfig(grid_config)) => {
+                            stream_data.set_grid_config(grid_config);
+                        }
+                        Err(mpsc::TryRecvError::Empty) => break,
+                        Err(mpsc::TryRecvError::Disconnected) => return,
+                    }
+                }
+
+                // checked before reading the slots, so nothing pushed before the producer went
+                // away is missed
+                let abandoned = samples.as_ref().is_some_and(|it| it.is_abandoned());
+                let available = samples.as_ref().map_or(0, |it| it.slots());
+                if available == 0 {
+                    if abandoned {
+                        samples = None;
+                        diagnostics.input_ended.store(true, Ordering::Relaxed);
+                    }
+                    std::thread::sleep(IDLE_SLEEP);
+                    continue;
+                }
+                let chunk = samples.as_mut().unwrap().read_chunk(available).unwrap();
+                let (first, second) = chunk.as_slices();
+                stream_data.append(first.iter().chain(second).copied());
+                chunk.commit_all();
+
+                while let Some(fft_data) = stream_data.take() {
+                    diagnostics.hops.fetch_add(1, Ordering::Relaxed);
+                    if lossless {
+                        if chunks.send(fft_data).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    match chunks.try_send(fft_data) {
+                        Ok(()) => {}
+                        Err(mpsc::TrySendError::Full(_)) => {
+                            diagnostics.dropped_chunks.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(mpsc::TrySendError::Disconnected(_)) => return,
+                    }
+                }
+            }
+        })
+        .expect("failed to spawn analysis thread")
+}
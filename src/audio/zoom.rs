@@ -0,0 +1,222 @@
+use std::{
+    f64::consts::{PI, TAU},
+    fmt::Display,
+    str::FromStr,
+    sync::Arc,
+};
+
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+
+use super::WindowFunction;
+
+/// Output rows across the band, about one per display row.
+const ROWS: usize = 1024;
+/// The frame is made long enough to resolve about this many frequencies across the band...
+const RESOLVED_FREQS: f32 = 64.0;
+/// ...within these limits, in samples.
+const MIN_FRAME: usize = 1 << 10;
+const MAX_FRAME: usize = 1 << 16;
+
+/// Frequency band of the zoom analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomConfig {
+    pub min_freq: f32,
+    pub max_freq: f32,
+}
+impl Default for ZoomConfig {
+    fn default() -> Self {
+        // mains hum at both 50 and 60 Hz
+        Self {
+            min_freq: 40.0,
+            max_freq: 80.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseZoomConfigError(String);
+impl Display for ParseZoomConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid zoom band {:?}, expected <min hz>-<max hz> like \"40-80\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseZoomConfigError {}
+
+impl FromStr for ZoomConfig {
+    type Err = ParseZoomConfigError;
+    /// `min-max`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseZoomConfigError(s.to_string());
+        let (min, max) = s.split_once('-').ok_or_else(err)?;
+        let config = Self {
+            min_freq: min.trim().parse().map_err(|_| err())?,
+            max_freq: max.trim().parse().map_err(|_| err())?,
+        };
+        if config.min_freq < 0.0 || config.max_freq <= config.min_freq {
+            return Err(err());
+        }
+        Ok(config)
+    }
+}
+impl Display for ZoomConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.min_freq, self.max_freq)
+    }
+}
+
+/// Chirp-Z transform of one band: the spectrum at [`ROWS`] evenly spaced frequencies between
+/// the band edges, however narrow, computed with Bluestein's algorithm as a convolution of
+/// two chirps.
+///
+/// The frame is long enough to actually resolve detail within the band (the spacing of the
+/// rows alone would only interpolate), and ends at the newest sample.
+#[derive(Clone)]
+pub struct ZoomTransform {
+    frame_len: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    /// Window, scaling and the input chirp, per frame sample.
+    pre: Arc<Vec<Complex32>>,
+    /// Spectrum of the convolving chirp, with the inverse FFT's 1/length folded in.
+    filter: Arc<Vec<Complex32>>,
+    freqs: Arc<Vec<f32>>,
+    buf: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+impl ZoomTransform {
+    pub fn new(config: ZoomConfig, window: WindowFunction, sample_rate: f32) -> Self {
+        let max_freq = config.max_freq.min(sample_rate / 2.0);
+        let min_freq = config.min_freq.min(max_freq);
+        let width = (max_freq - min_freq).max(f32::EPSILON);
+        let frame_len = ((RESOLVED_FREQS * sample_rate / width) as usize)
+            .next_power_of_two()
+            .clamp(MIN_FRAME, MAX_FRAME);
+        let len = (frame_len + ROWS - 1).next_power_of_two();
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(len);
+        let ifft = planner.plan_fft_inverse(len);
+
+        // row k is at min_freq + k * step. With n * k = (n² + k² - (k - n)²) / 2, the
+        // transform becomes a convolution with a chirp between two chirp multiplications.
+        // Phases are wrapped in f64 first, as n² gets large.
+        let step = (max_freq - min_freq) as f64 / (ROWS - 1) as f64 / sample_rate as f64;
+        let chirp = |n: f64| Complex32::from_polar(1.0, ((PI * step * n * n) % TAU) as f32);
+        let coefficients = window.coefficients(frame_len);
+        let scale = 2.0 / coefficients.iter().sum::<f32>();
+        let start = TAU * min_freq as f64 / sample_rate as f64;
+        let pre = coefficients
+            .iter()
+            .enumerate()
+            .map(|(n, w)| {
+                let shift = Complex32::from_polar(1.0, (-start * n as f64 % TAU) as f32);
+                shift * chirp(n as f64).conj() * w * scale
+            })
+            .collect();
+        let mut filter = vec![Complex32::zero(); len];
+        for (m, filter) in filter.iter_mut().take(ROWS).enumerate() {
+            *filter = chirp(m as f64);
+        }
+        for n in 1..frame_len {
+            filter[len - n] = chirp(n as f64);
+        }
+        fft.process(&mut filter);
+        filter.iter_mut().for_each(|it| *it /= len as f32);
+
+        let freqs = (0..ROWS)
+            .map(|k| min_freq + width * k as f32 / (ROWS - 1) as f32)
+            .collect();
+        Self {
+            frame_len,
+            scratch: vec![
+                Complex32::zero();
+                fft.get_inplace_scratch_len()
+                    .max(ifft.get_inplace_scratch_len())
+            ],
+            buf: vec![Complex32::zero(); len],
+            fft,
+            ifft,
+            pre: Arc::new(pre),
+            filter: Arc::new(filter),
+            freqs: Arc::new(freqs),
+        }
+    }
+    /// Samples needed per frame.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+    /// Number of output rows.
+    pub fn rows(&self) -> usize {
+        ROWS
+    }
+    /// Frequency in Hz of each row, ascending.
+    pub fn freqs(&self) -> Vec<f32> {
+        self.freqs.to_vec()
+    }
+    /// The band's edges in Hz.
+    pub fn band(&self) -> (f32, f32) {
+        (self.freqs[0], self.freqs[ROWS - 1])
+    }
+    /// Transform the last [`Self::frame_len`] samples of `frame` into `magnitude`, one row
+    /// each.
+    pub fn analyze(&mut self, frame: &[f32], magnitude: &mut [f32]) {
+        let frame = &frame[frame.len() - self.frame_len..];
+        self.buf.fill(Complex32::zero());
+        for ((buf, &sample), pre) in self.buf.iter_mut().zip(frame).zip(self.pre.iter()) {
+            *buf = pre * sample;
+        }
+        self.fft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        for (buf, filter) in self.buf.iter_mut().zip(self.filter.iter()) {
+            *buf *= filter;
+        }
+        self.ifft
+            .process_with_scratch(&mut self.buf, &mut self.scratch);
+        // the output chirp only changes the phase
+        for (magnitude, value) in magnitude.iter_mut().zip(&self.buf) {
+            *magnitude = value.norm();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_reads_0_dbfs_at_its_frequency() {
+        let sample_rate = 48000.0;
+        for (config, hz) in [
+            (ZoomConfig::default(), 50.0),
+            (ZoomConfig::default(), 61.3),
+            (
+                ZoomConfig {
+                    min_freq: 1000.0,
+                    max_freq: 3000.0,
+                },
+                2345.6,
+            ),
+        ] {
+            let mut zoom = ZoomTransform::new(config, WindowFunction::Hann, sample_rate);
+            let step = TAU * hz as f64 / sample_rate as f64;
+            let frame: Vec<f32> = (0..zoom.frame_len())
+                .map(|i| (step * i as f64).sin() as f32)
+                .collect();
+            let mut magnitude = vec![0.0; zoom.rows()];
+            zoom.analyze(&frame, &mut magnitude);
+
+            let peak = (0..zoom.rows())
+                .max_by(|&a, &b| magnitude[a].total_cmp(&magnitude[b]))
+                .unwrap();
+            let freqs = zoom.freqs();
+            let row_hz = freqs[1] - freqs[0];
+            let error = (freqs[peak] - hz).abs() / row_hz;
+            assert!(error <= 0.5, "{hz} Hz: peak off by {error} rows");
+            let db = 20.0 * magnitude[peak].log10();
+            assert!(db.abs() < 0.01, "{hz} Hz: {db} dBFS");
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::path::PathBuf;
+
+use spexia::{
+    audio::{AnalysisMode, ChannelMap, FftConfig, FileMode, GridConfig, Signal, SignalGenerator},
+    render::{ChannelLayout, ColorScale, FrequencyMapping, DEFAULT_HISTORY_MINUTES},
+    tuning::Tuning,
+    util::GenericResult,
+};
+
+const USAGE: &str = "\
+usage: spexia [options]
+       spexia render <file> -o <png> [render options]
+       spexia render --generate <signal> -o <png> [render options]
+
+options:
+    --channels <map>     channels to analyse: all, mono, midside, or 1-based channels
+                         like 3,4 (a+b averages device channels a and b into one channel)
+    --layout <layout>    how channels share the display: stacked, overlaid, or tinted
+                         (overlaid with a colour per channel)
+    --analysis <mode>    reassigned (default), synchrosqueezed, cqt, multires, cwt or zoom
+    --wavelet <wavelet>  wavelet of the cwt analysis: morlet, morlet:<omega0> (default 6)
+                         or mexican-hat
+    --cwt <scales>       wavelet scales: <voices per octave>,<min hz>,<max hz> (default
+                         24,32.703,16744)
+    --cqt <settings>     constant-Q bins: <bins per octave>,<min hz>,<max hz>, optionally
+                         followed by ,<hz> widening every bin for variable-Q (default
+                         24,32.703,16744)
+    --multires <bands>   multi-resolution FFT sizes from the lowest band up, separated by
+                         the crossover frequencies between them (default
+                         8192,250,2048,2000,512)
+    --zoom <hz>-<hz>     band of the zoom analysis, drawn over the full height (default
+                         40-80)
+    --scale <scale>      frequency scale of the display: log (default), linear, mel, bark
+                         or erb
+    --freq-range <hz>-<hz>
+                         frequencies at the bottom and top of the display (default
+                         19.03-19484, ten octaves)
+    --colormap <map>     colours of the spectrogram: heat (default), rainbow, viridis,
+                         magma, inferno or grayscale
+    --floor <dbfs>       level drawn in the colormap's first colour (default -60)
+    --ceiling <dbfs>     level drawn in its last colour (default -16)
+    --gamma <gamma>      curve between floor and ceiling, below 1 to bring out quiet
+                         detail (default 1)
+    --auto-gain          follow the signal's recent peak and noise floor instead of
+                         --floor and --ceiling
+    --a4 <hz>            reference pitch of the note grid and tuner (default 440)
+    --history <minutes>  scrollback kept for pausing with P (default 2), about 2 KB per
+                         channel per hop: 23 MB per channel and minute at 48 kHz with the
+                         default hop
+    --device <device>    device to capture from: default, a number or id from
+                         --list-devices, or part of a device name
+    --input              capture from an input device
+    --output             monitor an output device
+    --list-devices       list input and output devices and exit
+    --file <path>        analyse a wav or flac file instead of a device
+    --generate <signal>  analyse a test signal instead of a device: sine:<hz>,
+                         tones:<hz>,<hz>..., square:<hz>, chirp:<hz>-<hz>[:<seconds>],
+                         logchirp:<hz>-<hz>[:<seconds>], white, pink or impulses:<rate>
+    --level <dbfs>       peak level of the test signal (default -6)
+    --rate <hz>          sample rate of the test signal (default 48000)
+    --duration <seconds> stop the test signal after this long (default: never, or 5 s
+                         when rendering)
+    --playback           play the file or signal on the default output device while
+                         analysing it
+    --fast               analyse the file or signal as fast as possible instead of in real
+                         time
+    -h, --help           print this message
+
+--device, --input and --output are remembered for the next run.
+
+render options:
+    -o, --output <path>  png to write
+    --width <pixels>     image width (default 1024)
+    --height <pixels>    image height (default 1024)
+    --fft-size <n>       frame size, a power of two (default 2048)
+    --hop <n>            hop between frames, a power of two (default 256)
+    --channels <map>     as above
+    --layout <layout>    as above
+    --analysis <mode>    as above
+    --cqt <settings>     as above
+    --multires <bands>   as above
+    --wavelet <wavelet>  as above
+    --cwt <scales>       as above
+    --zoom <hz>-<hz>     as above
+    --scale <scale>      as above
+    --freq-range <hz>-<hz>
+                         as above
+    --colormap, --floor, --ceiling and --gamma as above
+    --generate, --level, --rate and --duration as above
+";
+
+/// What to do, picked by the first argument.
+pub enum Command {
+    /// Open the window.
+    View(Args),
+    /// Draw a file's spectrogram to a png without opening a window.
+    Render(RenderArgs),
+}
+
+/// Parse `std::env::args`. Prints usage and exits for `--help`.
+pub fn parse() -> GenericResult<Command> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("render") {
+        args.next();
+        Ok(Command::Render(RenderArgs::parse(args)?))
+    } else {
+        Ok(Command::View(Args::parse(args)?))
+    }
+}
+
+fn print_usage() -> ! {
+    print!("{USAGE}");
+    std::process::exit(0);
+}
+
+/// `--generate` and the options shaping the test signal.
+pub struct GeneratorArgs {
+    pub signal: Option<Signal>,
+    pub level: f32,
+    pub sample_rate: u32,
+    pub duration: Option<f32>,
+}
+impl Default for GeneratorArgs {
+    fn default() -> Self {
+        Self {
+            signal: None,
+            level: -6.0,
+            sample_rate: 48000,
+            duration: None,
+        }
+    }
+}
+impl GeneratorArgs {
+    /// The generator asked for with `--generate`, if any.
+    pub fn generator(&self) -> Option<SignalGenerator> {
+        let signal = self.signal.clone()?;
+        let generator = SignalGenerator::new(signal, self.level, self.sample_rate);
+        Some(match self.duration {
+            Some(seconds) => generator.with_duration(seconds),
+            None => generator,
+        })
+    }
+}
+
+fn parse_analysis(value: &str) -> GenericResult<AnalysisMode> {
+    AnalysisMode::from_name(value).ok_or_else(|| format!("unknown analysis mode {value:?}").into())
+}
+
+fn parse_layout(value: &str) -> GenericResult<ChannelLayout> {
+    match value {
+        "stacked" => Ok(ChannelLayout::Stacked),
+        "overlaid" => Ok(ChannelLayout::Overlaid),
+        "tinted" => Ok(ChannelLayout::Tinted),
+        other => Err(format!("unknown layout {other:?}").into()),
+    }
+}
+
+fn check_color_scale(color_scale: &ColorScale) -> GenericResult<()> {
+    let (floor, ceil) = (color_scale.db_floor, color_scale.db_ceil);
+    if !(floor.is_finite() && ceil.is_finite() && floor < ceil) {
+        return Err("--floor must be below --ceiling".into());
+    }
+    if !(color_scale.gamma > 0.0 && color_scale.gamma.is_finite()) {
+        return Err("--gamma must be positive".into());
+    }
+    Ok(())
+}
+
+/// Command line options.
+pub struct Args {
+    pub channel_map: ChannelMap,
+    pub channel_layout: ChannelLayout,
+    pub mode: AnalysisMode,
+    pub grid_config: GridConfig,
+    pub frequency_mapping: FrequencyMapping,
+    pub color_scale: ColorScale,
+    pub tuning: Tuning,
+    pub history_minutes: f32,
+    /// Unresolved `--device` query.
+    pub device: Option<String>,
+    /// `Some(true)` for `--input`, `Some(false)` for `--output`.
+    pub use_input: Option<bool>,
+    pub list_devices: bool,
+    pub file: Option<PathBuf>,
+    pub generator: GeneratorArgs,
+    pub file_mode: FileMode,
+}
+impl Args {
+    fn parse(mut it: impl Iterator<Item = String>) -> GenericResult<Self> {
+        let mut args = Self {
+            channel_map: ChannelMap::default(),
+            channel_layout: ChannelLayout::default(),
+            mode: AnalysisMode::default(),
+            grid_config: GridConfig::default(),
+            frequency_mapping: FrequencyMapping::default(),
+            color_scale: ColorScale::default(),
+            tuning: Tuning::default(),
+            history_minutes: DEFAULT_HISTORY_MINUTES,
+            device: None,
+            use_input: None,
+            list_devices: false,
+            file: None,
+            generator: GeneratorArgs::default(),
+            file_mode: FileMode::default(),
+        };
+        while let Some(arg) = it.next() {
+            let mut value = || {
+                it.next()
+                    .ok_or_else(|| format!("missing value for {arg}\n\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--channels" => args.channel_map = value()?.parse()?,
+                "--layout" => args.channel_layout = parse_layout(&value()?)?,
+                "--analysis" => args.mode = parse_analysis(&value()?)?,
+                "--cqt" => args.grid_config.cqt = value()?.parse()?,
+                "--multires" => args.grid_config.multires = value()?.parse()?,
+                "--wavelet" => args.grid_config.cwt.wavelet = value()?.parse()?,
+                "--cwt" => args.grid_config.cwt = args.grid_config.cwt.with_scales(&value()?)?,
+                "--zoom" => args.grid_config.zoom = value()?.parse()?,
+                "--scale" => args.frequency_mapping.scale = value()?.parse()?,
+                "--freq-range" => {
+                    args.frequency_mapping = args.frequency_mapping.with_range(&value()?)?
+                }
+                "--colormap" => args.color_scale.colormap = value()?.parse()?,
+                "--floor" => args.color_scale.db_floor = value()?.parse()?,
+                "--ceiling" => args.color_scale.db_ceil = value()?.parse()?,
+                "--gamma" => args.color_scale.gamma = value()?.parse()?,
+                "--auto-gain" => args.color_scale.auto_gain = true,
+                "--a4" => args.tuning = Tuning::new(value()?.parse()?),
+                "--history" => args.history_minutes = value()?.parse()?,
+                "--device" => args.device = Some(value()?),
+                "--input" => args.use_input = Some(true),
+                "--output" => args.use_input = Some(false),
+                "--list-devices" => args.list_devices = true,
+                "--file" => args.file = Some(value()?.into()),
+                "--generate" => args.generator.signal = Some(value()?.parse()?),
+                "--level" => args.generator.level = value()?.parse()?,
+                "--rate" => args.generator.sample_rate = value()?.parse()?,
+                "--duration" => args.generator.duration = Some(value()?.parse()?),
+                "--playback" => args.file_mode = FileMode::Playback,
+                "--fast" => args.file_mode = FileMode::Fast,
+                "-h" | "--help" => print_usage(),
+                _ => return Err(format!("unknown argument {arg:?}\n\n{USAGE}").into()),
+            }
+        }
+        if !(args.tuning.a4 > 0.0 && args.tuning.a4.is_finite()) {
+            return Err("--a4 must be a positive frequency".into());
+        }
+        if !(args.history_minutes > 0.0 && args.history_minutes.is_finite()) {
+            return Err("--history must be a positive number of minutes".into());
+        }
+        check_color_scale(&args.color_scale)?;
+        let generate = args.generator.signal.is_some();
+        if args.file.is_some() && generate {
+            return Err("--file and --generate cannot be used together".into());
+        }
+        if args.file.is_none() && !generate && args.file_mode != FileMode::default() {
+            return Err("--playback and --fast need --file or --generate".into());
+        }
+        Ok(args)
+    }
+}
+
+/// Options for `spexia render`.
+pub struct RenderArgs {
+    /// The file to render, unless rendering a test signal.
+    pub input: Option<PathBuf>,
+    pub generator: GeneratorArgs,
+    pub output: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub fft_config: FftConfig,
+    pub channel_map: ChannelMap,
+    pub channel_layout: ChannelLayout,
+    pub mode: AnalysisMode,
+    pub grid_config: GridConfig,
+    pub frequency_mapping: FrequencyMapping,
+    pub color_scale: ColorScale,
+}
+impl RenderArgs {
+    fn parse(mut it: impl Iterator<Item = String>) -> GenericResult<Self> {
+        let mut input = None;
+        let mut generator = GeneratorArgs::default();
+        let mut output = None;
+        let mut width = 1024;
+        let mut height = 1024;
+        let mut fft_config = FftConfig::default();
+        let mut channel_map = ChannelMap::default();
+        let mut channel_layout = ChannelLayout::default();
+        let mut mode = AnalysisMode::default();
+        let mut grid_config = GridConfig::default();
+        let mut frequency_mapping = FrequencyMapping::default();
+        let mut color_scale = ColorScale::default();
+        while let Some(arg) = it.next() {
+            let mut value = || {
+                it.next()
+                    .ok_or_else(|| format!("missing value for {arg}\n\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "--width" => width = value()?.parse()?,
+                "--height" => height = value()?.parse()?,
+                "--fft-size" => fft_config = FftConfig::new(value()?.parse()?, fft_config.stride),
+                "--hop" => fft_config = FftConfig::new(fft_config.size, value()?.parse()?),
+                "--channels" => channel_map = value()?.parse()?,
+                "--layout" => channel_layout = parse_layout(&value()?)?,
+                "--analysis" => mode = parse_analysis(&value()?)?,
+                "--cqt" => grid_config.cqt = value()?.parse()?,
+                "--multires" => grid_config.multires = value()?.parse()?,
+                "--wavelet" => grid_config.cwt.wavelet = value()?.parse()?,
+                "--cwt" => grid_config.cwt = grid_config.cwt.with_scales(&value()?)?,
+                "--zoom" => grid_config.zoom = value()?.parse()?,
+                "--scale" => frequency_mapping.scale = value()?.parse()?,
+                "--freq-range" => frequency_mapping = frequency_mapping.with_range(&value()?)?,
+                "--colormap" => color_scale.colormap = value()?.parse()?,
+                "--floor" => color_scale.db_floor = value()?.parse()?,
+                "--ceiling" => color_scale.db_ceil = value()?.parse()?,
+                "--gamma" => color_scale.gamma = value()?.parse()?,
+                "--generate" => generator.signal = Some(value()?.parse()?),
+                "--level" => generator.level = value()?.parse()?,
+                "--rate" => generator.sample_rate = value()?.parse()?,
+                "--duration" => generator.duration = Some(value()?.parse()?),
+                "-h" | "--help" => print_usage(),
+                _ if input.is_none() && !arg.starts_with('-') => input = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unknown argument {arg:?}\n\n{USAGE}").into()),
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err("--width and --height must be at least 1".into());
+        }
+        check_color_scale(&color_scale)?;
+        match (&input, &generator.signal) {
+            (None, None) => {
+                return Err(format!("render needs an input file or --generate\n\n{USAGE}").into())
+            }
+            (Some(_), Some(_)) => return Err("cannot render a file and --generate at once".into()),
+            // an endless signal would never finish
+            (None, Some(_)) => generator.duration = generator.duration.or(Some(5.0)),
+            (Some(_), None) => {}
+        }
+        Ok(Self {
+            input,
+            generator,
+            output: output.ok_or("render needs -o <png>")?,
+            width,
+            height,
+            fft_config,
+            channel_map,
+            channel_layout,
+            mode,
+            grid_config,
+            frequency_mapping,
+            color_scale,
+        })
+    }
+}
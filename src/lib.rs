@@ -0,0 +1,5 @@
+pub mod audio;
+pub mod render;
+pub mod settings;
+pub mod tuning;
+pub mod util;
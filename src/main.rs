@@ -1,15 +1,80 @@
-use audio::{DeviceSelector, Streamer};
-use render::Window;
-use util::{GenericResult, Vec2I};
+use spexia::{
+    audio::{
+        list_devices, ChannelMap, DeviceChoice, DeviceSelector, FftConfig, SpectrumScaling,
+        Streamer, WindowFunction,
+    },
+    render::{self, ColorScale, Window},
+    settings::Settings,
+    util::{GenericResult, Vec2I},
+};
 
-mod audio;
-mod render;
-mod util;
+mod cli;
+mod offline;
 
 fn main() -> GenericResult<()> {
+    match cli::parse()? {
+        cli::Command::View(args) => view(args),
+        cli::Command::Render(args) => offline::render(args),
+    }
+}
+
+/// The live spectrogram window.
+fn view(args: cli::Args) -> GenericResult<()> {
+    if args.list_devices {
+        for use_input in [true, false] {
+            println!("{} devices:", if use_input { "input" } else { "output" });
+            for (i, device) in list_devices(use_input).iter().enumerate() {
+                println!("{:4}: {}", i + 1, device);
+            }
+        }
+        return Ok(());
+    }
     println!("{:?}", cpal::available_hosts());
-    let mut audio_device_selector = DeviceSelector::new(false);
-    let mut audio = Streamer::begin(&audio_device_selector)?;
+    let mut settings = Settings::load();
+    let saved_input = settings.get("input") == Some("true");
+    let use_input = args.use_input.unwrap_or(saved_input);
+    let device_choice = match &args.device {
+        Some(query) => DeviceChoice::find(query, use_input)?,
+        // a saved device belongs to the saved direction
+        None if use_input == saved_input => settings
+            .get("device")
+            .and_then(|it| it.parse().ok())
+            .unwrap_or_default(),
+        None => DeviceChoice::Default,
+    };
+    let mut audio_device_selector = DeviceSelector::new(use_input, device_choice);
+    save_device(&mut settings, &audio_device_selector);
+    let mut fft_config = FftConfig::default();
+    let mut window_fn = WindowFunction::default();
+    let mut scaling = SpectrumScaling::default();
+    let mut mode = args.mode;
+    let mut grid_config = args.grid_config;
+    // channel maps cycled through with C: the one from the command line first
+    let mut channel_maps = vec![args.channel_map];
+    for channel_map in [ChannelMap::All, ChannelMap::Mono, ChannelMap::MidSide] {
+        if !channel_maps.contains(&channel_map) {
+            channel_maps.push(channel_map);
+        }
+    }
+    // with --file or --generate, the device selector is unused
+    let mut audio = Streamer::new(fft_config);
+    audio.set_channel_map(channel_maps[0].clone());
+    audio.set_mode(mode);
+    audio.set_grid_config(grid_config.clone());
+    let live = match (&args.file, args.generator.generator()) {
+        (Some(path), _) => {
+            audio.open_file(path, args.file_mode)?;
+            false
+        }
+        (None, Some(generator)) => {
+            audio.open_generator(generator, args.file_mode)?;
+            false
+        }
+        (None, None) => {
+            audio.update_stream(&audio_device_selector)?;
+            true
+        }
+    };
 
     //// initialize rendering ////
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
@@ -21,48 +86,192 @@ fn main() -> GenericResult<()> {
         "spexia spectrogram :3",
         |window| {
             window.set_key_polling(true);
+            window.set_cursor_pos_polling(true);
+            window.set_cursor_enter_polling(true);
+            window.set_scroll_polling(true);
+            window.set_mouse_button_polling(true);
         },
     );
-    let mut render_app = render::RenderApp::new();
+    let mut render_app = render::RenderApp::new(fft_config, 2, args.channel_layout);
+    render_app.set_tuning(args.tuning);
+    render_app.set_frequency_mapping(args.frequency_mapping);
+    render_app.set_history_minutes(args.history_minutes);
+    render_app.set_color_scale(args.color_scale);
+    if live {
+        render_app.show_message(&device_message(&audio_device_selector, &audio));
+    } else if args.file.is_some() {
+        render_app.show_message(&format!("file: {}", audio.device_name().unwrap_or("")));
+    } else {
+        render_app.show_message(&format!("signal: {}", audio.device_name().unwrap_or("")));
+    }
 
     let mut input_changed = false;
     //// program loop ////
     while !window.should_close() {
         //// audio system updates ////
-        if input_changed || audio_device_selector.poll_device_has_changed(audio.did_lose_device()) {
-            audio.update_stream(&audio_device_selector);
-            println!("updated stream");
+        if live
+            && (input_changed
+                || audio_device_selector.poll_device_has_changed(audio.did_lose_device()))
+        {
+            match audio.update_stream(&audio_device_selector) {
+                Ok(()) => println!("updated stream"),
+                Err(err) => eprintln!("{}", err),
+            }
+            render_app.show_message(&device_message(&audio_device_selector, &audio));
             input_changed = false;
         }
         // let mut updated = false;
-        {
-            let mut audio_data = audio.data.lock().unwrap();
-            while let Some(k) = audio_data.take() {
-                render_app.set_wave(&k, audio_data.sample_rate);
-            }
+        while let Some(k) = audio.take() {
+            render_app.set_wave(&k);
         }
 
         //// window polling and events ////
         glfw.poll_events();
         window.handle_events(|ev, winfo| match ev {
-            glfw::WindowEvent::Key(key, _scancode, action, _modifiers) => {
+            // held for zooming time with the mouse wheel
+            ev @ glfw::WindowEvent::Key(glfw::Key::LeftShift | glfw::Key::RightShift, ..) => {
+                render_app.handle_event(&ev)
+            }
+            glfw::WindowEvent::Key(key, _scancode, action, modifiers) => {
                 if let glfw::Action::Press = action {
                     match key {
                         glfw::Key::D => {
                             winfo.decorated = !winfo.decorated;
                         }
-                        glfw::Key::M => {
+                        glfw::Key::M if live => {
                             audio_device_selector.set_uses_input(!audio_device_selector.uses_input());
+                            save_device(&mut settings, &audio_device_selector);
+                            input_changed = true;
+                        }
+                        glfw::Key::O if live => {
+                            let backwards = modifiers.contains(glfw::Modifiers::Shift);
+                            audio_device_selector.cycle(!backwards);
+                            save_device(&mut settings, &audio_device_selector);
                             input_changed = true;
                         }
                         glfw::Key::T => {
                             winfo.floating = !winfo.floating;
                         }
+                        glfw::Key::I => {
+                            println!("{}", audio.diagnostics());
+                        }
+                        glfw::Key::C => {
+                            channel_maps.rotate_left(1);
+                            audio.set_channel_map(channel_maps[0].clone());
+                            println!("channels: {}", channel_maps[0]);
+                        }
+                        glfw::Key::A => {
+                            mode = mode.next();
+                            audio.set_mode(mode);
+                            println!("analysis: {}", mode.name());
+                        }
+                        glfw::Key::V => {
+                            grid_config.cwt.wavelet = grid_config.cwt.wavelet.next();
+                            audio.set_grid_config(grid_config.clone());
+                            println!("wavelet: {}", grid_config.cwt.wavelet.name());
+                        }
+                        glfw::Key::G => {
+                            render_app.set_show_notes(!render_app.show_notes());
+                            println!(
+                                "note grid: {} (A4 = {} Hz)",
+                                if render_app.show_notes() { "on" } else { "off" },
+                                render_app.tuning().a4
+                            );
+                        }
+                        // changing the scale clears the history being looked through
+                        glfw::Key::F if render_app.paused() => {
+                            render_app.show_message("resume to change the frequency scale");
+                        }
+                        glfw::Key::F => {
+                            let mut mapping = render_app.frequency_mapping();
+                            mapping.scale = mapping.scale.next();
+                            render_app.set_frequency_mapping(mapping);
+                            println!("frequency scale: {}", mapping.scale.name());
+                        }
+                        glfw::Key::P => {
+                            render_app.set_paused(!render_app.paused());
+                            let paused = render_app.paused();
+                            let text = if paused { "paused" } else { "resumed" };
+                            render_app.show_message(text);
+                            println!("{}", text);
+                        }
+                        glfw::Key::K => {
+                            let mut color_scale = render_app.color_scale();
+                            color_scale.colormap = color_scale.colormap.next();
+                            render_app.set_color_scale(color_scale);
+                            println!("colormap: {}", color_scale.colormap.name());
+                        }
+                        glfw::Key::Up | glfw::Key::Down => {
+                            // the ceiling, or with shift the floor
+                            let up = key == glfw::Key::Up;
+                            let mut color_scale = render_app.color_scale();
+                            if color_scale.auto_gain {
+                                // take over from where auto-gain left the levels
+                                let (floor, ceil) = render_app.db_range();
+                                color_scale.db_floor = floor.round();
+                                color_scale.db_ceil = ceil.round();
+                                color_scale.auto_gain = false;
+                                println!("auto-gain off");
+                            }
+                            render_app.set_color_scale(
+                                if modifiers.contains(glfw::Modifiers::Shift) {
+                                    color_scale.step_floor(up)
+                                } else {
+                                    color_scale.step_ceil(up)
+                                },
+                            );
+                            print_levels(render_app.color_scale());
+                        }
+                        glfw::Key::Semicolon | glfw::Key::Apostrophe => {
+                            let color_scale = render_app.color_scale();
+                            let up = key == glfw::Key::Apostrophe;
+                            render_app.set_color_scale(color_scale.step_gamma(up));
+                            print_levels(render_app.color_scale());
+                        }
+                        glfw::Key::U => {
+                            let mut color_scale = render_app.color_scale();
+                            color_scale.auto_gain = !color_scale.auto_gain;
+                            render_app.set_color_scale(color_scale);
+                            print_levels(color_scale);
+                        }
+                        glfw::Key::R => {
+                            render_app.reset_view();
+                            println!("view reset");
+                        }
+                        glfw::Key::X => {
+                            render_app.set_show_axes(!render_app.show_axes());
+                            let shown = render_app.show_axes();
+                            println!("axes: {}", if shown { "on" } else { "off" });
+                        }
+                        glfw::Key::L => {
+                            let channel_layout = render_app.channel_layout().next();
+                            render_app.set_channel_layout(channel_layout);
+                            println!("channel layout: {}", channel_layout.name());
+                        }
+                        glfw::Key::LeftBracket | glfw::Key::RightBracket => {
+                            fft_config = fft_config.step_size(key == glfw::Key::RightBracket);
+                            audio.set_fft_config(fft_config);
+                            println!("fft size: {}, hop: {}", fft_config.size, fft_config.stride);
+                        }
+                        glfw::Key::Comma | glfw::Key::Period => {
+                            fft_config = fft_config.step_stride(key == glfw::Key::Period);
+                            audio.set_fft_config(fft_config);
+                            println!("fft size: {}, hop: {}", fft_config.size, fft_config.stride);
+                        }
+                        glfw::Key::W | glfw::Key::Minus | glfw::Key::Equal | glfw::Key::N => {
+                            match key {
+                                glfw::Key::W => window_fn = window_fn.next(),
+                                glfw::Key::N => scaling = scaling.next(),
+                                _ => window_fn = window_fn.step_param(key == glfw::Key::Equal),
+                            }
+                            audio.set_window(window_fn, scaling);
+                            println!("window: {}, scaling: {}", window_fn.name(), scaling.name());
+                        }
                         _ => {}
                     }
                 }
             }
-            _ => {}
+            ev => render_app.handle_event(&ev),
         });
 
         //// render ////
@@ -71,8 +280,46 @@ fn main() -> GenericResult<()> {
         // if i != 0 {
         //     continue;
         // }
+        render_app.set_viewport(window.size());
+        render_app.prepare_draw();
         window.render(|winfo| render_app.draw(&winfo));
     }
 
     Ok(())
 }
+
+/// Report the colour scale's levels after changing them.
+fn print_levels(color_scale: ColorScale) {
+    if color_scale.auto_gain {
+        println!("levels: auto-gain, gamma {:.2}", color_scale.gamma);
+    } else {
+        println!(
+            "levels: {} to {} dBFS, gamma {:.2}",
+            color_scale.db_floor, color_scale.db_ceil, color_scale.gamma
+        );
+    }
+}
+
+/// Remember the device choice for the next run.
+fn save_device(settings: &mut Settings, device_selector: &DeviceSelector) {
+    settings.set("input", device_selector.uses_input());
+    settings.set("device", device_selector.choice());
+    if let Err(err) = settings.save() {
+        eprintln!("could not save settings: {}", err);
+    }
+}
+
+/// The on-screen note shown whenever the capture device changes.
+fn device_message(device_selector: &DeviceSelector, audio: &Streamer) -> String {
+    let direction = if device_selector.uses_input() {
+        "input"
+    } else {
+        "output"
+    };
+    let following = match device_selector.choice() {
+        DeviceChoice::Default => " (default)",
+        DeviceChoice::Device(_) => "",
+    };
+    let name = audio.device_name().unwrap_or("no usable device");
+    format!("{}{}: {}", direction, following, name)
+}
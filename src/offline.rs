@@ -0,0 +1,97 @@
+use std::{fs::File, io::BufWriter, path::Path, thread, time::Duration};
+
+use spexia::{
+    audio::{AudioFile, FileMode, Streamer},
+    render::{OfflineRenderer, Window},
+    util::GenericResult,
+};
+
+use crate::cli::RenderArgs;
+
+/// `spexia render`: analyse the whole file or test signal as fast as possible and draw it to
+/// a png.
+pub fn render(args: RenderArgs) -> GenericResult<()> {
+    // a hidden window, only so there is a GL context to draw with
+    let mut glfw = glfw::init(glfw::fail_on_errors)?;
+    let _window = Window::new_hidden(&mut glfw);
+
+    let mut audio = Streamer::new(args.fft_config);
+    audio.set_channel_map(args.channel_map);
+    audio.set_mode(args.mode);
+    audio.set_grid_config(args.grid_config);
+    let frames = match (&args.input, args.generator.generator()) {
+        (Some(path), _) => {
+            let frames = count_frames(path)?;
+            audio.open_file(path, FileMode::Fast)?;
+            frames
+        }
+        (None, Some(generator)) => {
+            let frames = generator
+                .frames()
+                .expect("rendered signals have a duration") as usize;
+            audio.open_generator(generator, FileMode::Fast)?;
+            frames
+        }
+        (None, None) => unreachable!("checked when parsing"),
+    };
+    // a frame is analysed once `size + 1` samples have arrived, then every `stride`
+    let total_hops = frames
+        .checked_sub(args.fft_config.size + 1)
+        .map_or(0, |rest| rest / args.fft_config.stride + 1);
+    let mut renderer = OfflineRenderer::new(
+        args.width,
+        args.height,
+        total_hops,
+        args.channel_layout,
+        args.frequency_mapping,
+        args.color_scale,
+    )?;
+    loop {
+        // chunks are all queued by the time the input is marked as ended
+        let ended = audio.input_ended();
+        while let Some(chunk) = audio.take() {
+            renderer.push(&chunk);
+        }
+        if ended {
+            break;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    let pixels = renderer.finish();
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(&args.output)?),
+        args.width as u32,
+        args.height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    println!(
+        "wrote {} ({}x{}, {} hops)",
+        args.output.display(),
+        args.width,
+        args.height,
+        total_hops
+    );
+    Ok(())
+}
+
+/// The file's length in frames, to lay its hops out across the image.
+fn count_frames(path: &Path) -> GenericResult<usize> {
+    let mut file = AudioFile::open(path)?;
+    if let Some(frames) = file.frames {
+        return Ok(frames as usize);
+    }
+    // the header doesn't say, so decode the whole file once
+    let mut buffer = vec![0.0; 4096 * file.channels];
+    let mut samples = 0;
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        samples += n;
+    }
+    Ok(samples / file.channels)
+}
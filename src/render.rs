@@ -1,44 +1,713 @@
-use rustfft::num_complex::{Complex32, ComplexFloat};
+use std::{ops::Range, time::Instant};
+
+use rustfft::num_complex::ComplexFloat;
 
 use crate::{
-    audio::{FFT_SIZE, FFT_STRIDE, HALF_FFT_SIZE},
+    audio::{AnalysisMode, AudioDataChunk, FftConfig, SpectrumGrid},
     glrs_renderable,
+    tuning::{Note, Tuning},
+    util::Vec2I,
 };
 
-use self::glrs::{GLParam::*, Triangle};
+use self::{
+    color::{AutoGain, ColorLut},
+    glrs::{GLParam::*, Triangle},
+    history::MagnitudeHistory,
+    view::ViewState,
+};
 
+mod axes;
+mod color;
+mod font;
 mod glfwrs;
 mod glrs;
+mod history;
+mod offline;
+mod scale;
+mod view;
 
+pub use color::{ColorScale, Colormap, ParseColormapError};
 pub use glfwrs::{Window};
+pub use glrs::GLError;
+pub use offline::OfflineRenderer;
+pub use scale::{
+    FrequencyMapping, FrequencyScale, ParseFrequencyRangeError, ParseFrequencyScaleError,
+};
 
 pub const NUM_SPECTROGRAM_FRAMES: usize = 1024;
+/// Frequency rows of the texture grid-based analyses are resampled to.
+const GRID_ROWS: usize = 1024;
+/// GLSL pulled into shaders by an `#include "<name>"` line.
+const SHADER_INCLUDES: [(&str, &str); 2] = [
+    ("freq_scale.glsl", include_str!("shader/freq_scale.glsl")),
+    ("color_scale.glsl", include_str!("shader/color_scale.glsl")),
+];
+/// Pitch confidence needed to retune the waveline's trigger period.
+const TRIGGER_CONFIDENCE: f32 = 0.8;
+/// Zoom factor per step of the mouse wheel.
+const ZOOM_STEP: f32 = 1.25;
+/// Scrollback kept unless [`RenderApp::set_history_minutes`] says otherwise.
+pub const DEFAULT_HISTORY_MINUTES: f32 = 2.0;
+/// The level readout takes the loudest of at least this many rows either side of the
+/// cursor, as the reassigned spectrum's points leave the rows between them empty.
+const READOUT_ROWS: f32 = 2.0;
+/// Level a spectrum peak needs for the tuner to show it, about -60 dBFS.
+const TUNER_MIN_LEVEL: f32 = 1e-3;
+/// How long a message from [`RenderApp::show_message`] stays up, and how much of that is
+/// spent fading out.
+const MESSAGE_SECONDS: f32 = 3.0;
+const MESSAGE_FADE_SECONDS: f32 = 0.5;
+
+/// Expand the `#include` lines of a shader's source, which GLSL itself has no notion of.
+pub(crate) fn shader_source(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| {
+            let name = line
+                .strip_prefix("#include \"")
+                .and_then(|it| it.strip_suffix('"'));
+            match name {
+                Some(name) => {
+                    SHADER_INCLUDES
+                        .iter()
+                        .find(|(include, _)| *include == name)
+                        .unwrap_or_else(|| panic!("unknown shader include {name:?}"))
+                        .1
+                }
+                None => line,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How multiple analysed channels share the spectrogram area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelLayout {
+    /// Each channel gets its own horizontal band, first channel on top.
+    #[default]
+    Stacked,
+    /// All channels drawn over the full area.
+    Overlaid,
+    /// Overlaid, each channel in its own colour: first red, second cyan, so a centred stereo
+    /// source adds up to white.
+    Tinted,
+}
+impl ChannelLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Stacked => "stacked",
+            Self::Overlaid => "overlaid",
+            Self::Tinted => "tinted",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Stacked => Self::Overlaid,
+            Self::Overlaid => Self::Tinted,
+            Self::Tinted => Self::Stacked,
+        }
+    }
+    /// Bottom edge and height (as fractions of the display) of channel `ch` of `channels`.
+    fn band(&self, ch: usize, channels: usize) -> (f32, f32) {
+        match self {
+            Self::Stacked => {
+                let height = 1.0 / channels as f32;
+                (1.0 - (ch + 1) as f32 * height, height)
+            }
+            Self::Overlaid | Self::Tinted => (0.0, 1.0),
+        }
+    }
+    /// Number of distinct bands `channels` channels are drawn in.
+    fn bands(&self, channels: usize) -> usize {
+        match self {
+            Self::Stacked => channels,
+            Self::Overlaid | Self::Tinted => 1,
+        }
+    }
+}
 
 pub struct RenderApp {
     render_spectrogram: RenderSpectrogram,
     render_reassigned_spectrogram: RenderReassignedSpectrogram,
+    render_grid: RenderGrid,
+    render_pitch: RenderPitch,
+    render_notes: RenderNotes,
+    render_note_labels: RenderText,
+    render_tuner: RenderText,
+    axes: axes::Axes,
+    render_crosshair: axes::RenderTicks,
+    render_readout: RenderText,
+    history: MagnitudeHistory,
+    // what is drawn while paused, loaded from the history
+    render_scrollback: RenderGrid,
+    render_scrollback_pitch: RenderPitch,
     render_waveline: RenderWaveline,
     render_floatingindicator: RenderFloatingIndicator,
+    render_message: RenderText,
+    message_shown: Option<Instant>,
 
-    wave_last: [[f32; FFT_SIZE]; 2],
+    wave_last: Vec<Vec<f32>>,
 
+    fft_config: FftConfig,
+    channels: usize,
+    channel_layout: ChannelLayout,
+    mode: AnalysisMode,
+    tuning: Tuning,
+    frequency_mapping: FrequencyMapping,
+    view: ViewState,
+    /// Latest cursor position in pixels, while it is over the window.
+    cursor: Option<(f32, f32)>,
+    /// Whether the view is being dragged with the mouse.
+    dragging: bool,
+    /// Whether a shift key is held, turning the mouse wheel to zoom time while paused.
+    shift_held: bool,
+    /// Whether the display is frozen on the history, while capture goes on.
+    paused: bool,
+    /// Frames captured since pausing.
+    paused_frames: usize,
+    /// Placement of the history loaded into `render_scrollback`, as its `x_range`.
+    scrollback_x_range: (f32, f32),
+    /// Whether the paused view moved since the scrollback was last loaded.
+    scrollback_dirty: bool,
+    history_minutes: f32,
+    color_scale: ColorScale,
+    color_lut: ColorLut,
+    auto_gain: AutoGain,
+    /// Floor and ceiling the legend was last labelled for, rounded to whole dB.
+    legend_range: (f32, f32),
+    /// Whether the note grid, its labels and the tuner are drawn.
+    show_notes: bool,
+    show_axes: bool,
+    /// Window size in pixels, which the labels and tuner are laid out for.
+    viewport: Vec2I,
+    tuner_text: String,
+    /// Sample rate of the latest chunk, which sets the time axis.
+    sample_rate: f32,
+    /// The band the latest chunk's grid spreads linearly over the display, if any, which
+    /// overrides `frequency_mapping`.
+    linear_range: Option<(f32, f32)>,
     frame_n: usize,
 }
 
 impl RenderApp {
-    pub fn new() -> Self {
+    pub fn new(fft_config: FftConfig, channels: usize, channel_layout: ChannelLayout) -> Self {
         Self {
-            render_spectrogram: RenderSpectrogram::new(),
-            render_reassigned_spectrogram: RenderReassignedSpectrogram::new(),
-            render_waveline: RenderWaveline::new(),
+            render_spectrogram: RenderSpectrogram::new(fft_config),
+            render_reassigned_spectrogram: RenderReassignedSpectrogram::new(fft_config, channels),
+            render_grid: RenderGrid::new(channels),
+            render_pitch: RenderPitch::new(channels),
+            render_notes: RenderNotes::new(),
+            render_note_labels: RenderText::new(),
+            render_tuner: RenderText::new(),
+            axes: axes::Axes::new(),
+            render_crosshair: axes::RenderTicks::new(),
+            render_readout: RenderText::new(),
+            history: MagnitudeHistory::new(channels, 0),
+            render_scrollback: RenderGrid::new(channels),
+            render_scrollback_pitch: RenderPitch::new(channels),
+            render_waveline: RenderWaveline::new(fft_config, channels),
             render_floatingindicator: RenderFloatingIndicator::new(),
+            render_message: RenderText::new(),
+            message_shown: None,
 
-            wave_last: [[0.0; FFT_SIZE]; 2],
+            wave_last: vec![vec![0.0; fft_config.size]; channels],
 
+            fft_config,
+            channels,
+            channel_layout,
+            mode: AnalysisMode::default(),
+            tuning: Tuning::default(),
+            frequency_mapping: FrequencyMapping::default(),
+            view: ViewState::default(),
+            cursor: None,
+            dragging: false,
+            shift_held: false,
+            paused: false,
+            paused_frames: 0,
+            scrollback_x_range: (0.0, 1.0),
+            scrollback_dirty: false,
+            history_minutes: DEFAULT_HISTORY_MINUTES,
+            color_scale: ColorScale::default(),
+            color_lut: ColorLut::new(ColorScale::default()),
+            auto_gain: AutoGain::default(),
+            legend_range: (0.0, 0.0),
+            show_notes: true,
+            show_axes: true,
+            viewport: Vec2I(0, 0),
+            tuner_text: String::new(),
+            sample_rate: 0.0,
+            linear_range: None,
             frame_n: 0,
         }
     }
 
+    /// Reallocate every size-dependent gpu buffer for new analysis parameters, channel count
+    /// or mode. The scrolling history and scrollback are cleared, since old frames have a
+    /// different layout, and the display resumes if paused, saying so.
+    fn resize(&mut self, fft_config: FftConfig, channels: usize, mode: AnalysisMode) {
+        if self.paused {
+            self.set_paused(false);
+            self.show_message("resumed: history cleared");
+        }
+        self.render_spectrogram = RenderSpectrogram::new(fft_config);
+        self.render_reassigned_spectrogram = RenderReassignedSpectrogram::new(fft_config, channels);
+        self.render_grid = RenderGrid::new(channels);
+        self.render_pitch = RenderPitch::new(channels);
+        let hops_per_minute = 60.0 * self.sample_rate / fft_config.stride as f32;
+        let capacity = (self.history_minutes * hops_per_minute) as usize;
+        self.history = MagnitudeHistory::new(channels, capacity.max(NUM_SPECTROGRAM_FRAMES));
+        self.render_scrollback = RenderGrid::new(channels);
+        self.render_scrollback_pitch = RenderPitch::new(channels);
+        self.mode = mode;
+        self.render_waveline = RenderWaveline::new(fft_config, channels);
+        self.wave_last = vec![vec![0.0; fft_config.size]; channels];
+        self.fft_config = fft_config;
+        self.channels = channels;
+        self.frame_n = 0;
+        self.layout();
+    }
+
+    /// Set how long a history to keep for scrolling back through while paused. The history
+    /// is cleared.
+    pub fn set_history_minutes(&mut self, minutes: f32) {
+        self.history_minutes = minutes;
+        self.resize(self.fft_config, self.channels, self.mode);
+    }
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+    /// Freeze the display on the latest frames, so the history can be scrolled through by
+    /// dragging and zoomed with shift and the mouse wheel, or go back to the live display.
+    /// Capture goes on either way.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused && !paused {
+            // the point cloud was left as it was when pausing, so the slots since then hold
+            // frames from a whole history earlier
+            let frames = self.paused_frames.min(NUM_SPECTROGRAM_FRAMES);
+            for k in 0..frames {
+                let frame_n = (self.frame_n + NUM_SPECTROGRAM_FRAMES - k) % NUM_SPECTROGRAM_FRAMES;
+                self.render_reassigned_spectrogram.clear_frame(frame_n);
+            }
+        }
+        self.paused = paused;
+        self.paused_frames = 0;
+        self.view.reset_time();
+        self.scrollback_dirty = paused;
+        self.layout();
+    }
+
+    pub fn color_scale(&self) -> ColorScale {
+        self.color_scale
+    }
+    pub fn set_color_scale(&mut self, color_scale: ColorScale) {
+        self.color_scale = color_scale;
+        self.update_color_range();
+    }
+    /// Floor and ceiling drawn, in dBFS: the colour scale's own, or auto-gain's if it is on.
+    pub fn db_range(&self) -> (f32, f32) {
+        self.color_lut.db_range()
+    }
+    /// Apply the colour scale, following auto-gain if it is on, and relabel the legend
+    /// once its levels have moved.
+    fn update_color_range(&mut self) {
+        let auto_range = if self.color_scale.auto_gain {
+            self.auto_gain.db_range()
+        } else {
+            None
+        };
+        self.color_lut.set(self.color_scale, auto_range);
+        let (floor, ceil) = self.color_lut.db_range();
+        if (floor.round(), ceil.round()) != self.legend_range {
+            self.layout();
+        }
+    }
+
+    pub fn channel_layout(&self) -> ChannelLayout {
+        self.channel_layout
+    }
+    pub fn set_channel_layout(&mut self, channel_layout: ChannelLayout) {
+        self.channel_layout = channel_layout;
+        self.layout();
+    }
+
+    pub fn tuning(&self) -> Tuning {
+        self.tuning
+    }
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.tuning = tuning;
+        self.layout();
+    }
+    pub fn frequency_mapping(&self) -> FrequencyMapping {
+        self.frequency_mapping
+    }
+    /// Change how frequencies are spread over the display. The history is cleared, as it was
+    /// laid out for the old mapping.
+    pub fn set_frequency_mapping(&mut self, frequency_mapping: FrequencyMapping) {
+        self.frequency_mapping = frequency_mapping;
+        self.resize(self.fft_config, self.channels, self.mode);
+    }
+    /// The mapping of the unzoomed display: a grid's own linear band, if it has one.
+    fn display_mapping(&self) -> FrequencyMapping {
+        match self.linear_range {
+            Some((lo, hi)) => FrequencyMapping::linear(lo, hi),
+            None => self.frequency_mapping,
+        }
+    }
+    /// The mapping of the visible part of the display.
+    fn visible_mapping(&self) -> FrequencyMapping {
+        self.view.mapping(self.display_mapping())
+    }
+
+    /// Zoom the frequency axis with the mouse wheel around the cursor, and pan it by dragging.
+    /// While paused, dragging also scrolls through the history and the wheel zooms time
+    /// with shift held.
+    pub fn handle_event(&mut self, event: &glfw::WindowEvent) {
+        match *event {
+            glfw::WindowEvent::CursorPos(x, y) => {
+                let (x, y) = (x as f32, y as f32);
+                let last = self.cursor;
+                self.cursor = Some((x, y));
+                if let (true, Some((last_x, last_y))) = (self.dragging, last) {
+                    let (_, _, band_pixels) = self.band_at(y);
+                    // the content follows the cursor
+                    self.view.pan_freq((y - last_y) / band_pixels);
+                    if self.paused {
+                        let width = self.viewport.0.max(1) as f32;
+                        self.view
+                            .pan_time((x - last_x) / width, self.scrollback_frames());
+                        self.scrollback_dirty = true;
+                    }
+                    self.layout();
+                } else {
+                    self.update_readout();
+                }
+            }
+            glfw::WindowEvent::CursorEnter(false) => {
+                self.cursor = None;
+                self.dragging = false;
+                self.update_readout();
+            }
+            glfw::WindowEvent::Scroll(_, steps) => {
+                let factor = ZOOM_STEP.powf(-steps as f32);
+                if let (Some((x, _)), true) = (self.cursor, self.paused && self.shift_held) {
+                    let anchor = x / self.viewport.0.max(1) as f32;
+                    self.view
+                        .zoom_time(anchor, factor, self.scrollback_frames());
+                    self.scrollback_dirty = true;
+                    self.layout();
+                } else if let Some((_, y)) = self.cursor {
+                    let (_, anchor, _) = self.band_at(y);
+                    self.view.zoom_freq(anchor, factor);
+                    self.layout();
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, action, _) => {
+                self.dragging = action != glfw::Action::Release;
+            }
+            glfw::WindowEvent::Key(glfw::Key::LeftShift | glfw::Key::RightShift, _, action, _) => {
+                self.shift_held = action != glfw::Action::Release;
+            }
+            _ => {}
+        }
+    }
+    /// Go back to the full frequency range, and while paused to the frames shown when
+    /// pausing.
+    pub fn reset_view(&mut self) {
+        self.view = ViewState::default();
+        self.scrollback_dirty = self.paused;
+        self.layout();
+    }
+    /// Frames of history from the moment of pausing back.
+    fn scrollback_frames(&self) -> usize {
+        self.history.len().saturating_sub(self.paused_frames)
+    }
+    /// Reload the scrollback if the paused view moved. Events only mark it as needed, as
+    /// dragging sends many more of them than frames are drawn; call this before each
+    /// [`Self::draw`].
+    pub fn prepare_draw(&mut self) {
+        if self.scrollback_dirty {
+            self.load_scrollback();
+        }
+    }
+    /// Fill the scrollback renderers with the part of the history the paused view shows:
+    /// up to a column per frame, or zoomed out, the loudest of several frames per column.
+    fn load_scrollback(&mut self) {
+        const FRAMES: usize = NUM_SPECTROGRAM_FRAMES;
+        self.scrollback_dirty = false;
+        let (scroll, span) = self.view.time_window();
+        let per_column = (span / FRAMES as f32).ceil().max(1.0) as usize;
+        // whole columns from just right of the view, the newest last
+        let first = (scroll / per_column as f32) as usize * per_column;
+        let offset = scroll - first as f32;
+        let columns = (((offset + span) / per_column as f32).ceil() as usize).min(FRAMES);
+        let mut all_levels = vec![0; FRAMES * GRID_ROWS];
+        for ch in 0..self.channels {
+            all_levels.fill(0);
+            for (c, levels) in all_levels.chunks_exact_mut(GRID_ROWS).enumerate() {
+                let mut pitch = [0.0f32; 2];
+                // columns past the newest are left silent, off the right edge
+                let ages = match columns.checked_sub(c + 1) {
+                    Some(k) => {
+                        let newest = self.paused_frames + first + k * per_column;
+                        newest..newest + per_column
+                    }
+                    None => 0..0,
+                };
+                for age in ages {
+                    if let Some(rows) = self.history.rows(ch, age) {
+                        for (level, &row) in levels.iter_mut().zip(rows) {
+                            *level = (*level).max(row);
+                        }
+                    }
+                    match self.history.pitch(ch, age) {
+                        Some(it) if it[1] > pitch[1] => pitch = it,
+                        _ => {}
+                    }
+                }
+                self.render_scrollback_pitch
+                    .set_frame(c, ch, pitch[0], pitch[1]);
+            }
+            self.render_scrollback.set_columns(ch, &all_levels);
+        }
+        // stretched so the newest column's right edge is `offset` frames past the view's
+        let width = (FRAMES * per_column) as f32 / span;
+        let left = 1.0 + (offset - (columns * per_column) as f32) / span;
+        self.scrollback_x_range = (left, width);
+    }
+    /// The band window row `y` (in pixels from the top) falls in, the height within it from
+    /// 0 at the bottom to 1 at the top, and the band's height in pixels.
+    fn band_at(&self, y: f32) -> (usize, f32, f32) {
+        let height = self.viewport.1.max(1) as f32;
+        let display_y = 1.0 - y / height;
+        let bands = self.channel_layout.bands(self.channels);
+        let (j, (band_lo, band_height)) = (0..bands)
+            .map(|j| (j, self.channel_layout.band(j, self.channels)))
+            .find(|(_, (lo, band_height))| display_y >= *lo && display_y <= lo + band_height)
+            .unwrap_or((0, (0.0, 1.0)));
+        (j, (display_y - band_lo) / band_height, band_height * height)
+    }
+
+    /// Draw a crosshair at the cursor, with the frequency, nearest note, time before now and
+    /// level of each channel under it.
+    fn update_readout(&mut self) {
+        self.render_crosshair.clear();
+        self.render_readout.clear();
+        if let (Some((x, y)), true) = (self.cursor, self.sample_rate > 0.0) {
+            let (width, height) = (self.viewport.0 as f32, self.viewport.1 as f32);
+            self.render_crosshair.push([0.0, y - 0.5, width, y + 0.5]);
+            self.render_crosshair.push([x - 0.5, 0.0, x + 0.5, height]);
+
+            let (band, band_y, band_pixels) = self.band_at(y);
+            let freq = self.visible_mapping().to_freq(band_y);
+            let (note, cents) = self.tuning.nearest(freq);
+            // frames before now, or before pausing, the newest at the right edge
+            let (scroll, span) = self.view.time_window();
+            let hops = (scroll + (1.0 - x / width) * span).max(0.0) as usize;
+            let age = hops + self.paused_frames;
+            let seconds = (hops * self.fft_config.stride) as f32 / self.sample_rate;
+            let mut text = format!(
+                "{freq:.1} Hz  {note} {:+} cents\n-{seconds:.3} s",
+                cents.round() as i32
+            );
+
+            let (view_lo, view_height) = self.view.freq_view();
+            let display_y = view_lo + view_height * band_y;
+            // the rows under the cursor's pixel, and when zoomed in a few either side
+            let spread = (view_height / band_pixels / 2.0).max(READOUT_ROWS / GRID_ROWS as f32);
+            let channels = match self.channel_layout {
+                ChannelLayout::Stacked => band..band + 1,
+                ChannelLayout::Overlaid | ChannelLayout::Tinted => 0..self.channels,
+            };
+            for ch in channels {
+                let rows = display_y - spread..display_y + spread;
+                let magnitude = self.history.get(ch, age, rows);
+                let db = 20.0 * magnitude.max(1e-6).log10();
+                text += &if self.channels > 1 {
+                    format!("\nch {}: {db:.1} dBFS", ch + 1)
+                } else {
+                    format!("\n{db:.1} dBFS")
+                };
+            }
+
+            // beside the cursor, on whichever side has room
+            let (text_width, text_height) = font::text_size(&text);
+            let (text_width, text_height) = (text_width as f32, text_height as f32);
+            let text_x = if x + 12.0 + text_width < width {
+                x + 12.0
+            } else {
+                x - 12.0 - text_width
+            };
+            let text_y = if y + 12.0 + text_height < height {
+                y + 12.0
+            } else {
+                y - 12.0 - text_height
+            };
+            self.render_readout.push(
+                &text,
+                (text_x, text_y),
+                1.0,
+                glrs::Rgba {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                    a: 1.0,
+                },
+            );
+        }
+        self.render_crosshair.update();
+        self.render_readout.update();
+    }
+    pub fn show_notes(&self) -> bool {
+        self.show_notes
+    }
+    /// Show or hide the note grid, its labels and the tuner.
+    pub fn set_show_notes(&mut self, show_notes: bool) {
+        self.show_notes = show_notes;
+    }
+    pub fn show_axes(&self) -> bool {
+        self.show_axes
+    }
+    /// Show or hide the frequency and time axes and the colour scale legend.
+    pub fn set_show_axes(&mut self, show_axes: bool) {
+        self.show_axes = show_axes;
+    }
+
+    /// Lay out anything placed in pixels for a window of `size`. Cheap when nothing changed,
+    /// so it can be called every frame.
+    pub fn set_viewport(&mut self, size: Vec2I) {
+        if size != self.viewport {
+            self.viewport = size;
+            self.layout();
+            self.layout_tuner();
+        }
+    }
+
+    /// Lay out the note labels and axes after anything they depend on changed.
+    fn layout(&mut self) {
+        self.layout_note_labels();
+        let (scroll, span) = self.view.time_window();
+        let hop_seconds = self.fft_config.stride as f32 / self.sample_rate;
+        let time_range = if self.sample_rate > 0.0 {
+            (scroll * hop_seconds, (scroll + span) * hop_seconds)
+        } else {
+            (0.0, 0.0)
+        };
+        let (floor, ceil) = self.color_lut.db_range();
+        self.legend_range = (floor.round(), ceil.round());
+        self.axes.layout(
+            self.viewport,
+            self.channels,
+            self.channel_layout,
+            self.visible_mapping(),
+            time_range,
+            (floor, ceil),
+        );
+        self.update_readout();
+    }
+
+    /// Label every C of the note grid at the left edge of each band.
+    fn layout_note_labels(&mut self) {
+        let height = self.viewport.1 as f32;
+        let color = glrs::Rgba {
+            r: 0.8,
+            g: 0.85,
+            b: 1.0,
+            a: 0.8,
+        };
+        let mapping = self.visible_mapping();
+        let labels = &mut self.render_note_labels;
+        labels.clear();
+        let (lowest, _) = self.tuning.nearest(mapping.min_freq.max(1.0));
+        let (highest, _) = self.tuning.nearest(mapping.max_freq);
+        for j in 0..self.channel_layout.bands(self.channels) {
+            let (band_lo, band_height) = self.channel_layout.band(j, self.channels);
+            let mut last_y = f32::INFINITY;
+            for note in (lowest.0..=highest.0).map(Note).filter(Note::is_c) {
+                let y = mapping.to_display(self.tuning.freq(note));
+                // labels cut off by the band's edges would be ambiguous
+                if !(0.02..=0.98).contains(&y) {
+                    continue;
+                }
+                let y = (1.0 - (band_lo + band_height * y)) * height;
+                // where the scale squeezes octaves together, skip labels that would overlap
+                if last_y - y < font::LINE_HEIGHT as f32 {
+                    continue;
+                }
+                last_y = y;
+                let text = note.to_string();
+                labels.push(
+                    &text,
+                    (4.0, y - font::GLYPH_HEIGHT as f32 / 2.0),
+                    1.0,
+                    color,
+                );
+            }
+        }
+        labels.update();
+    }
+
+    /// Place the tuner readout in the top-right corner.
+    fn layout_tuner(&mut self) {
+        self.render_tuner.clear();
+        if !self.tuner_text.is_empty() {
+            let scale = 2.0;
+            let (width, _) = font::text_size(&self.tuner_text);
+            let x = self.viewport.0 as f32 - width as f32 * scale - 12.0;
+            self.render_tuner.push(
+                &self.tuner_text,
+                (x, 12.0),
+                scale,
+                glrs::Rgba {
+                    r: 0.8,
+                    g: 0.85,
+                    b: 1.0,
+                    a: 1.0,
+                },
+            );
+        }
+        self.render_tuner.update();
+    }
+
+    /// Show the nearest note to the strongest spectral peak across channels, at its
+    /// reassigned frequency, and how far off it is.
+    fn set_tuner(&mut self, wave: &AudioDataChunk) {
+        let half_size = wave.fft_config.half_size();
+        let peak = (0..wave.channels())
+            .flat_map(|ch| (1..half_size).map(move |i| (ch, i)))
+            .map(|(ch, i)| (wave.spectrum[ch][i].abs(), wave.freq[ch][i]))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let text = match peak {
+            Some((level, freq)) if level > TUNER_MIN_LEVEL && freq > 0.0 => {
+                let (note, cents) = self.tuning.nearest(freq);
+                format!("{note} {:+} cents ({freq:.1} Hz)", cents.round() as i32)
+            }
+            _ => String::new(),
+        };
+        if text != self.tuner_text {
+            self.tuner_text = text;
+            self.layout_tuner();
+        }
+    }
+
+    /// Briefly show `text` in the top-left corner of the window.
+    pub fn show_message(&mut self, text: &str) {
+        self.render_message.clear();
+        self.render_message.push(
+            text,
+            (12.0, 12.0),
+            2.0,
+            glrs::Rgba {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            },
+        );
+        self.render_message.update();
+        self.message_shown = Some(Instant::now());
+    }
+
     pub fn draw(&self, winfo: &glfwrs::Winfo) {
         // glrs::Rgba::TRANSPARENT.gl_clear_color();
         glrs::Rgba {
@@ -49,28 +718,117 @@ impl RenderApp {
         }
         .gl_clear_color();
 
-        self.render_reassigned_spectrogram
-            .render(self.frame_n, winfo);
-        // self.render_spectrogram.render(self.frame_n, winfo);
+        let mapping = self.visible_mapping();
+        // the point cloud stays as it was when pausing, and is shown until time is moved, as
+        // the history only keeps a level per row
+        let frozen_points = self.paused && !self.mode.is_grid() && self.view.time_is_default();
+        if self.paused && !frozen_points {
+            self.render_scrollback.draw(
+                0.0,
+                NUM_SPECTROGRAM_FRAMES,
+                self.channel_layout,
+                &self.color_lut,
+                self.scrollback_x_range,
+                self.view.freq_view(),
+            );
+        } else if self.mode.is_grid() {
+            self.render_grid.render(
+                self.frame_n,
+                self.channel_layout,
+                &self.color_lut,
+                self.view.freq_view(),
+            );
+        } else {
+            let frame_n = if self.paused {
+                let paused = self.paused_frames % NUM_SPECTROGRAM_FRAMES;
+                (self.frame_n + NUM_SPECTROGRAM_FRAMES - paused) % NUM_SPECTROGRAM_FRAMES
+            } else {
+                self.frame_n
+            };
+            self.render_reassigned_spectrogram.render(
+                frame_n,
+                mapping,
+                self.channel_layout,
+                &self.color_lut,
+                winfo,
+            );
+        }
+        if self.show_notes {
+            self.render_notes
+                .render(self.tuning, mapping, self.channels, self.channel_layout);
+            self.render_note_labels.render(1.0, winfo);
+        }
+        if self.paused {
+            // loaded oldest first from the start, as if the newest were the last frame
+            self.render_scrollback_pitch.render(
+                NUM_SPECTROGRAM_FRAMES - 1,
+                mapping,
+                self.channel_layout,
+                self.scrollback_x_range,
+            );
+        } else {
+            self.render_pitch
+                .render(self.frame_n, mapping, self.channel_layout, (0.0, 1.0));
+        }
+        if self.show_notes {
+            self.render_tuner.render(1.0, winfo);
+        }
+        if self.show_axes {
+            self.axes.render(&self.color_lut, winfo);
+        }
+        if self.cursor.is_some() {
+            self.render_crosshair.render(
+                glrs::Rgba {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                    a: 0.35,
+                },
+                winfo,
+            );
+            self.render_readout.render(1.0, winfo);
+        }
+        // self.render_spectrogram
+        //     .render(self.frame_n, mapping, self.sample_rate, winfo);
         self.render_waveline.render();
 
+        if let Some(shown) = self.message_shown {
+            let remaining = MESSAGE_SECONDS - shown.elapsed().as_secs_f32();
+            if remaining > 0.0 {
+                let opacity = (remaining / MESSAGE_FADE_SECONDS).min(1.0);
+                self.render_message.render(opacity, winfo);
+            }
+        }
+
         if winfo.floating {
             self.render_floatingindicator.render(winfo);
         }
     }
 
-    pub fn set_wave(
-        &mut self,
-        wave: &(
-            [[Complex32; FFT_SIZE]; 2],
-            [[f32; FFT_SIZE]; 2],
-            [[f32; HALF_FFT_SIZE]; 2],
-        ),
-        sample_rate: f32,
-    ) {
-        self.render_waveline
-            .set_wave(wave, &self.wave_last, sample_rate);
-        self.wave_last = wave.1;
+    pub fn set_wave(&mut self, wave: &AudioDataChunk) {
+        if wave.fft_config != self.fft_config
+            || wave.channels() != self.channels
+            || wave.mode != self.mode
+            || wave.sample_rate != self.sample_rate
+        {
+            // the sample rate sets how many frames the history holds
+            self.sample_rate = wave.sample_rate;
+            self.resize(wave.fft_config, wave.channels(), wave.mode);
+        }
+        let linear_range = wave.grid.as_ref().and_then(|it| it.linear_range);
+        if linear_range != self.linear_range {
+            self.linear_range = linear_range;
+            self.layout();
+        }
+        if !self.paused {
+            self.render_waveline.set_wave(
+                wave,
+                &self.wave_last,
+                wave.sample_rate,
+                self.visible_mapping(),
+            );
+        }
+        self.wave_last.clone_from(&wave.wave);
 
         {
             self.frame_n += 1;
@@ -79,8 +837,51 @@ impl RenderApp {
             }
         }
 
-        self.render_reassigned_spectrogram
-            .set_wave(self.frame_n, wave);
+        self.history.push();
+        if self.paused {
+            self.paused_frames += 1;
+        }
+
+        self.render_pitch.set_wave(self.frame_n, wave);
+        for ch in 0..self.channels {
+            let [freq, confidence] = self.render_pitch.frame(self.frame_n, ch);
+            self.history.set_pitch(ch, freq, confidence);
+        }
+        if self.show_notes && !self.paused {
+            self.set_tuner(wave);
+        }
+        match &wave.grid {
+            // stored unzoomed, so zooming applies to the whole history
+            Some(grid) => {
+                self.render_grid
+                    .set_wave(self.frame_n, grid, self.display_mapping());
+                for (ch, levels) in self.render_grid.levels().iter().enumerate() {
+                    self.history.set_rows(ch, levels);
+                }
+            }
+            None => {
+                if !self.paused {
+                    self.render_reassigned_spectrogram
+                        .set_wave(self.frame_n, wave);
+                }
+                let half = wave.fft_config.size / 2;
+                for ch in 0..self.channels {
+                    self.history.set_points(
+                        ch,
+                        self.display_mapping(),
+                        &wave.freq[ch],
+                        wave.spectrum[ch][..half].iter().map(|c| c.abs()),
+                    );
+                }
+            }
+        }
+        if self.color_scale.auto_gain && !self.paused {
+            let hop_seconds = wave.fft_config.stride as f32 / wave.sample_rate;
+            let columns = (0..self.channels).filter_map(|ch| self.history.rows(ch, 0));
+            self.auto_gain.push(columns, hop_seconds);
+            self.update_color_range();
+        }
+        self.update_readout();
         // self.render_spectrogram.set_wave(self.frame_n, wave);
     }
 }
@@ -95,37 +896,38 @@ glrs_renderable! {
     pub RenderSpectrogram(glrs::TriPosVO<2>) {
         shaders(vert: "./shader/spectrogram.vsh", frag: "./shader/spectrogram.fsh");
         vo(glrs::TriPosVO::new(SPECTROGRAM_DISPLAY_VERTS));
-        fn new() {
+        fn new(fft_config: FftConfig) {
             Self {
                 shaders, vo,
-                tex: glrs::GLTexture2d::new(),
-                spec_accum: [[0.0; 2]; HALF_FFT_SIZE],
+                tex: glrs::GLTexture2d::new(NUM_SPECTROGRAM_FRAMES, fft_config.half_size()),
+                spec_accum: vec![[0.0; 2]; fft_config.half_size()],
             }
         };
 
-        tex: glrs::GLTexture2d<NUM_SPECTROGRAM_FRAMES, HALF_FFT_SIZE>,
-        spec_accum: [[f64; 2]; HALF_FFT_SIZE],
+        tex: glrs::GLTexture2d,
+        spec_accum: Vec<[f64; 2]>,
     }
 }
 impl RenderSpectrogram {
-    pub fn render(&self, frame_n: usize, winfo: &glfwrs::Winfo) {
+    pub fn render(
+        &self,
+        frame_n: usize,
+        mapping: FrequencyMapping,
+        sample_rate: f32,
+        winfo: &glfwrs::Winfo,
+    ) {
         self.bind();
         self.tex.bind(glrs::GLTextureSlot::Tex0, 1);
         glrs::uniform(2, V1F(frame_n as f32 / NUM_SPECTROGRAM_FRAMES as f32));
         glrs::uniform(3, V1F(winfo.bounds.dim.1 as f32));
+        glrs::uniform(4, V1F(sample_rate / 2.0));
+        mapping.set_uniforms();
         glrs::DrawArrays::Triangles { range: 0..2 }.exec();
     }
-    pub fn set_wave(
-        &mut self,
-        frame_n: usize,
-        wave: &(
-            [[Complex32; FFT_SIZE]; 2],
-            [[f32; FFT_SIZE]; 2],
-            [[f32; HALF_FFT_SIZE]; 2],
-        ),
-    ) {
-        let mut ds = [glrs::Rgba::default(); FFT_SIZE / 2];
-        for i in 0..FFT_SIZE / 2 {
+    pub fn set_wave(&mut self, frame_n: usize, wave: &AudioDataChunk) {
+        let half_fft_size = wave.fft_config.half_size();
+        let mut ds = vec![glrs::Rgba::default(); half_fft_size];
+        for i in 0..half_fft_size {
             // for j in 0..2 {
             //     let cur = wave.0[j][i].abs() as f64;
             //     self.spec_accum[i][j] = cur + 0.5 * (self.spec_accum[i][j] - cur);
@@ -134,9 +936,11 @@ impl RenderSpectrogram {
             // let c1 = self.spec_accum[i][1].log10() as f32 / 100.0 + 0.5;
             let mut c = [0.0; 2];
             for j in 0..2 {
-                let cur = wave.0[j][i].abs() as f64;
+                // packs the first two channels; a mono chunk fills both
+                let cur = wave.spectrum[j.min(wave.channels() - 1)][i].abs() as f64;
                 self.spec_accum[i][j] = cur + 0.5 * (self.spec_accum[i][j] - cur);
-                c[j] = self.spec_accum[i][j].log10() as f32 / 100.0 + 0.5;
+                // +3 (60 dB) keeps the shader's calibration from before magnitudes were normalized
+                c[j] = (self.spec_accum[i][j].log10() + 3.0) as f32 / 100.0 + 0.5;
                 // if i == 200 && j == 0 {
                 //     dbg!(c[j]);
                 // }
@@ -149,105 +953,418 @@ impl RenderSpectrogram {
             };
         }
         if frame_n == NUM_SPECTROGRAM_FRAMES - 1 {
-            let mut d = [[glrs::Rgba::default(); 1]; FFT_SIZE / 2];
-            for i in 0..FFT_SIZE / 2 {
-                d[i] = [ds[i]; 1];
-            }
-            self.tex.update_partial(frame_n, 0, d);
-            self.tex.update_partial(0, 0, d);
+            self.tex.update_partial(frame_n, 0, 1, &ds);
+            self.tex.update_partial(0, 0, 1, &ds);
         } else {
-            let mut d = [[glrs::Rgba::default(); 2]; FFT_SIZE / 2];
-            for i in 0..FFT_SIZE / 2 {
-                d[i] = [ds[i]; 2];
-            }
-            self.tex.update_partial(frame_n, 0, d);
+            let d: Vec<_> = ds.iter().flat_map(|&c| [c; 2]).collect();
+            self.tex.update_partial(frame_n, 0, 2, &d);
         }
     }
 }
 
-const K: usize = NUM_SPECTROGRAM_FRAMES * HALF_FFT_SIZE * 2;
 glrs_renderable! {
-    pub RenderReassignedSpectrogram(glrs::BoxedF32VO<K, 3>) {
+    pub RenderReassignedSpectrogram(glrs::BoxedF32VO<4>) {
         shaders(vert: "./shader/reassigned.vsh", frag: "./shader/reassigned.fsh");
-        vo(glrs::BoxedF32VO::new());
-        fn new() {
-            Self { vo, shaders }
+        vo(glrs::BoxedF32VO::new(NUM_SPECTROGRAM_FRAMES * fft_config.half_size() * channels));
+        fn new(fft_config: FftConfig, channels: usize) {
+            Self {
+                vo, shaders,
+                half_fft_size: fft_config.half_size(),
+                channels,
+            }
         };
+
+        half_fft_size: usize,
+        channels: usize,
     }
 }
 impl RenderReassignedSpectrogram {
-    pub fn render(&self, frame_n: usize, _winfo: &glfwrs::Winfo) {
-        self.bind();
-        glrs::uniform(
-            1,
-            V1F(((frame_n + 1) as f32 / NUM_SPECTROGRAM_FRAMES as f32) % 1.0),
+    pub fn render(
+        &self,
+        frame_n: usize,
+        mapping: FrequencyMapping,
+        channel_layout: ChannelLayout,
+        color_lut: &ColorLut,
+        _winfo: &glfwrs::Winfo,
+    ) {
+        let n_frac = ((frame_n + 1) as f32 / NUM_SPECTROGRAM_FRAMES as f32) % 1.0;
+        self.draw(
+            n_frac,
+            NUM_SPECTROGRAM_FRAMES,
+            mapping,
+            channel_layout,
+            color_lut,
+            (0.0, 1.0),
+            1.0,
         );
+    }
+    /// Draw frames `0..frames` of every channel, scrolled left by `n_frac` of the history.
+    /// `x_range` is where the history lies across the viewport, as left edge and width.
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &self,
+        n_frac: f32,
+        frames: usize,
+        mapping: FrequencyMapping,
+        channel_layout: ChannelLayout,
+        color_lut: &ColorLut,
+        x_range: (f32, f32),
+        point_size: f32,
+    ) {
+        self.bind();
+        mapping.set_uniforms();
+        color_lut.set_uniforms();
+        glrs::uniform(1, V1F(n_frac));
+        glrs::uniform(4, V2F(x_range.0, x_range.1));
+        let tinted = channel_layout == ChannelLayout::Tinted;
+        glrs::uniform(5, V1F(if tinted { 1.0 } else { 0.0 }));
         glrs::TransparencyMode::Add.apply();
-        for j in 0..2 {
+        for j in 0..self.channels {
             glrs::uniform(2, V1F(j as f32));
-            let off = (NUM_SPECTROGRAM_FRAMES * HALF_FFT_SIZE) as i32 * j;
+            let (band_lo, band_height) = channel_layout.band(j, self.channels);
+            glrs::uniform(3, V2F(band_lo, band_height));
+            let off = (NUM_SPECTROGRAM_FRAMES * self.half_fft_size * j) as i32;
             glrs::DrawArrays::Points {
-                range: off..off + (HALF_FFT_SIZE * NUM_SPECTROGRAM_FRAMES) as i32,
-                point_size: 1.0,
+                range: off..off + (self.half_fft_size * frames) as i32,
+                point_size,
             }
             .exec();
         }
         glrs::TransparencyMode::Normal.apply();
     }
 
-    pub fn set_wave(
-        &mut self,
-        frame_n: usize,
-        wave: &(
-            [[Complex32; FFT_SIZE]; 2],
-            [[f32; FFT_SIZE]; 2],
-            [[f32; HALF_FFT_SIZE]; 2],
-        ),
-    ) {
-        for j in 0..2 {
-            let i0 = HALF_FFT_SIZE * (frame_n + j * NUM_SPECTROGRAM_FRAMES);
-            for i in 0..HALF_FFT_SIZE {
+    /// Empty frame `frame_n` of every channel.
+    pub fn clear_frame(&mut self, frame_n: usize) {
+        for j in 0..self.channels {
+            let i0 = self.half_fft_size * (frame_n + j * NUM_SPECTROGRAM_FRAMES);
+            self.vo.data[i0..i0 + self.half_fft_size].fill([0.0; 4]);
+            self.vo.update_range(i0..i0 + self.half_fft_size);
+        }
+    }
+    pub fn set_wave(&mut self, frame_n: usize, wave: &AudioDataChunk) {
+        // reassigned times, from seconds to fractions of the history
+        let time_scale =
+            wave.sample_rate / (wave.fft_config.stride * NUM_SPECTROGRAM_FRAMES) as f32;
+        for j in 0..self.channels {
+            let i0 = self.half_fft_size * (frame_n + j * NUM_SPECTROGRAM_FRAMES);
+            for i in 0..self.half_fft_size {
                 let x = frame_n as f32 / NUM_SPECTROGRAM_FRAMES as f32;
                 // let y = i as f32 / (HALF_FFT_SIZE) as f32;
-                let y = wave.2[0][i];
+                let y = wave.freq[j][i];
                 // let y = wave.2[0][i] * 0.1 + 0.9 * (i as f32 / (HALF_FFT_SIZE) as f32);
                 // self.vo.data[i + i1][2] = 0.0;
                 self.vo.data[i + i0] = [
                     x,
                     y,
                     // self.vo.data[i + il][1] * 0.25 + y * 0.75,
-                    wave.0[0][i].abs(),
+                    wave.spectrum[j][i].abs(),
+                    wave.time[j][i] * time_scale,
                 ];
             }
+            self.vo.update_range(i0..i0 + self.half_fft_size);
         }
-        self.vo.update();
     }
 }
 
-const WAVE_VO_SIZE: usize = 5 * FFT_SIZE;
 glrs_renderable! {
-    pub RenderWaveline(glrs::F32VO<WAVE_VO_SIZE, 2>) {
-        shaders(vert: "./shader/waveline.vsh", frag: "./shader/waveline.fsh");
-        vo(glrs::F32VO::new([[0.0; 2]; WAVE_VO_SIZE]));
+    pub RenderGrid(glrs::TriPosVO<2>) {
+        shaders(vert: "./shader/grid.vsh", frag: "./shader/grid.fsh");
+        vo(glrs::TriPosVO::new(SPECTROGRAM_DISPLAY_VERTS));
+        fn new(channels: usize) {
+            let textures = (0..channels)
+                .map(|_| {
+                    let tex = glrs::GLTexture2d::new(NUM_SPECTROGRAM_FRAMES, GRID_ROWS);
+                    tex.set_filter(glrs::GLTextureFilter::Nearest);
+                    tex
+                })
+                .collect();
+            Self {
+                shaders, vo,
+                textures,
+                column: vec![glrs::Rgba::default(); GRID_ROWS],
+                levels: vec![vec![0; GRID_ROWS]; channels],
+            }
+        };
+
+        // one history texture per channel, a column per frame and a row per display row
+        textures: Vec<glrs::GLTexture2d>,
+        column: Vec<glrs::Rgba<u8>>,
+        // the last column's levels per channel, before packing
+        levels: Vec<Vec<u16>>,
+    }
+}
+impl RenderGrid {
+    pub fn render(
+        &self,
+        frame_n: usize,
+        channel_layout: ChannelLayout,
+        color_lut: &ColorLut,
+        freq_view: (f32, f32),
+    ) {
+        let n_frac = ((frame_n + 1) as f32 / NUM_SPECTROGRAM_FRAMES as f32) % 1.0;
+        self.draw(
+            n_frac,
+            NUM_SPECTROGRAM_FRAMES,
+            channel_layout,
+            color_lut,
+            (0.0, 1.0),
+            freq_view,
+        );
+    }
+    /// Like [`RenderReassignedSpectrogram::draw`]. `freq_view` is the bottom and height of
+    /// the rows shown, as fractions of the texture.
+    fn draw(
+        &self,
+        n_frac: f32,
+        frames: usize,
+        channel_layout: ChannelLayout,
+        color_lut: &ColorLut,
+        x_range: (f32, f32),
+        freq_view: (f32, f32),
+    ) {
+        self.bind();
+        color_lut.set_uniforms();
+        glrs::uniform(1, V1F(n_frac));
+        glrs::uniform(4, V2F(x_range.0, x_range.1));
+        glrs::uniform(8, V2F(freq_view.0, freq_view.1));
+        let tinted = channel_layout == ChannelLayout::Tinted;
+        glrs::uniform(5, V1F(if tinted { 1.0 } else { 0.0 }));
+        glrs::uniform(7, V1F(frames as f32 / NUM_SPECTROGRAM_FRAMES as f32));
+        glrs::TransparencyMode::Add.apply();
+        for (j, tex) in self.textures.iter().enumerate() {
+            tex.bind(glrs::GLTextureSlot::Tex0, 6);
+            glrs::uniform(2, V1F(j as f32));
+            let (band_lo, band_height) = channel_layout.band(j, self.textures.len());
+            glrs::uniform(3, V2F(band_lo, band_height));
+            glrs::DrawArrays::Triangles { range: 0..2 }.exec();
+        }
+        glrs::TransparencyMode::Normal.apply();
+    }
+
+    /// Resample `grid` onto the display rows laid out by `mapping` and store it as column
+    /// `frame_n`.
+    pub fn set_wave(&mut self, frame_n: usize, grid: &SpectrumGrid, mapping: FrequencyMapping) {
+        let freq_at = |r: f32| mapping.to_freq(r / GRID_ROWS as f32);
+        for (ch, magnitude) in grid.magnitude.iter().enumerate().take(self.textures.len()) {
+            let mut levels = std::mem::take(&mut self.levels[ch]);
+            let mut next = 0;
+            for (r, stored) in levels.iter_mut().enumerate() {
+                let (lo, hi) = (freq_at(r as f32), freq_at(r as f32 + 1.0));
+                // the loudest grid row within the display row, or where the grid is coarser
+                // than the display, interpolated between the neighbouring grid rows
+                let first = next + grid.freqs[next..].partition_point(|&f| f < lo);
+                next = first + grid.freqs[first..].partition_point(|&f| f < hi);
+                let level = if next > first {
+                    magnitude[first..next].iter().copied().fold(0.0, f32::max)
+                } else if first == 0 || first == grid.freqs.len() {
+                    0.0
+                } else {
+                    let (f0, f1) = (grid.freqs[first - 1], grid.freqs[first]);
+                    let t = ((lo + hi) / 2.0 - f0) / (f1 - f0);
+                    magnitude[first - 1] + t * (magnitude[first] - magnitude[first - 1])
+                };
+                *stored = encode_level(level);
+            }
+            self.set_column(frame_n, ch, &levels);
+            self.levels[ch] = levels;
+        }
+    }
+    /// Store column `frame_n` of channel `ch` from levels per display row.
+    pub fn set_column(&mut self, frame_n: usize, ch: usize, levels: &[u16]) {
+        for (texel, &level) in self.column.iter_mut().zip(levels) {
+            *texel = pack_level(level);
+        }
+        self.textures[ch].update_partial(frame_n, 0, 1, &self.column);
+    }
+    /// Store the first columns of channel `ch` in one upload, from `levels` holding one
+    /// column of levels per display row after another.
+    pub fn set_columns(&mut self, ch: usize, levels: &[u16]) {
+        let frames = levels.len() / GRID_ROWS;
+        // the texture is stored a row at a time
+        let texels: Vec<_> = (0..GRID_ROWS)
+            .flat_map(|r| (0..frames).map(move |c| pack_level(levels[c * GRID_ROWS + r])))
+            .collect();
+        self.textures[ch].update_partial(0, 0, frames, &texels);
+    }
+    /// Level per display row of each channel, as stored by the last [`Self::set_wave`].
+    pub fn levels(&self) -> &[Vec<u16>] {
+        &self.levels
+    }
+}
+
+/// A magnitude as 16 bit dB, -120 to +20 dBFS, the way the grid textures and the history
+/// store levels.
+fn encode_level(magnitude: f32) -> u16 {
+    let db = 20.0 * magnitude.max(1e-12).log10();
+    (((db + 120.0) / 140.0).clamp(0.0, 1.0) * 65535.0) as u16
+}
+
+/// The dBFS of a level from [`encode_level`].
+fn level_to_db(level: u16) -> f32 {
+    level as f32 / 65535.0 * 140.0 - 120.0
+}
+
+/// The magnitude of a level from [`encode_level`].
+fn decode_level(level: u16) -> f32 {
+    10f32.powf(level_to_db(level) / 20.0)
+}
+
+/// Put a level from [`encode_level`] into the first two bytes of a texel, for `grid.fsh`
+/// to unpack.
+fn pack_level(level: u16) -> glrs::Rgba<u8> {
+    glrs::Rgba {
+        r: (level >> 8) as u8,
+        g: level as u8,
+        b: 0,
+        a: 0,
+    }
+}
+
+glrs_renderable! {
+    pub RenderPitch(glrs::BoxedF32VO<3>) {
+        shaders(vert: "./shader/pitch.vsh", frag: "./shader/pitch.fsh");
+        vo(glrs::BoxedF32VO::new(NUM_SPECTROGRAM_FRAMES * channels));
+        fn new(channels: usize) {
+            Self {
+                vo, shaders,
+                channels,
+                last_freq: vec![0.0; channels],
+            }
+        };
+
+        channels: usize,
+        // latest fundamental of each channel, held through frames without one so the line
+        // fades out flat instead of diving
+        last_freq: Vec<f32>,
+    }
+}
+impl RenderPitch {
+    /// Draw each channel's fundamental over the history as a line, faded where the estimate
+    /// is unsure. `x_range` is as for [`RenderGrid::draw`].
+    pub fn render(
+        &self,
+        frame_n: usize,
+        mapping: FrequencyMapping,
+        channel_layout: ChannelLayout,
+        x_range: (f32, f32),
+    ) {
+        let n_frac = ((frame_n + 1) as f32 / NUM_SPECTROGRAM_FRAMES as f32) % 1.0;
+        self.bind();
+        mapping.set_uniforms();
+        glrs::uniform(1, V1F(n_frac));
+        glrs::uniform(4, V2F(x_range.0, x_range.1));
+        let newest = frame_n as i32 + 1;
+        for j in 0..self.channels {
+            let (band_lo, band_height) = channel_layout.band(j, self.channels);
+            glrs::uniform(3, V2F(band_lo, band_height));
+            let off = (NUM_SPECTROGRAM_FRAMES * j) as i32;
+            // oldest and newest parts separately, so the line doesn't cross the display where
+            // the history wraps around
+            for range in [newest..NUM_SPECTROGRAM_FRAMES as i32, 0..newest] {
+                if range.end - range.start < 2 {
+                    continue;
+                }
+                glrs::DrawArrays::LineStrip {
+                    range: off + range.start..off + range.end,
+                    line_width: 2.0,
+                    point_size: 1.0,
+                }
+                .exec();
+            }
+        }
+    }
+    pub fn set_wave(&mut self, frame_n: usize, wave: &AudioDataChunk) {
+        for (j, pitch) in wave.pitch.iter().enumerate().take(self.channels) {
+            match pitch {
+                Some(pitch) => self.set_frame(frame_n, j, pitch.freq, pitch.confidence),
+                None => self.set_frame(frame_n, j, 0.0, 0.0),
+            }
+        }
+    }
+    /// Store channel `ch`'s fundamental at frame `frame_n`, or hold the last one with no
+    /// confidence if `freq` is 0.
+    pub fn set_frame(&mut self, frame_n: usize, ch: usize, freq: f32, confidence: f32) {
+        let x = frame_n as f32 / NUM_SPECTROGRAM_FRAMES as f32;
+        let vertex = if freq > 0.0 {
+            self.last_freq[ch] = freq;
+            [x, freq, confidence]
+        } else {
+            [x, self.last_freq[ch], 0.0]
+        };
+        let i = frame_n + ch * NUM_SPECTROGRAM_FRAMES;
+        self.vo.data[i] = vertex;
+        self.vo.update_range(i..i + 1);
+    }
+    /// Channel `ch`'s fundamental at frame `frame_n` and how sure it is.
+    pub fn frame(&self, frame_n: usize, ch: usize) -> [f32; 2] {
+        let [_, freq, confidence] = self.vo.data[frame_n + ch * NUM_SPECTROGRAM_FRAMES];
+        [freq, confidence]
+    }
+}
+
+glrs_renderable! {
+    pub RenderNotes(glrs::TriPosVO<2>) {
+        shaders(vert: "./shader/grid.vsh", frag: "./shader/notes.fsh");
+        vo(glrs::TriPosVO::new(SPECTROGRAM_DISPLAY_VERTS));
         fn new() {
             Self {
                 shaders, vo,
+            }
+        };
+    }
+}
+impl RenderNotes {
+    /// Draw a line at every note of `tuning` across each channel's band.
+    pub fn render(
+        &self,
+        tuning: Tuning,
+        mapping: FrequencyMapping,
+        channels: usize,
+        channel_layout: ChannelLayout,
+    ) {
+        self.bind();
+        mapping.set_uniforms();
+        glrs::uniform(1, V1F(tuning.a4));
+        glrs::uniform(4, V2F(0.0, 1.0));
+        for j in 0..channel_layout.bands(channels) {
+            let (band_lo, band_height) = channel_layout.band(j, channels);
+            glrs::uniform(3, V2F(band_lo, band_height));
+            glrs::DrawArrays::Triangles { range: 0..2 }.exec();
+        }
+    }
+}
+
+glrs_renderable! {
+    pub RenderWaveline(glrs::BoxedF32VO<2>) {
+        shaders(vert: "./shader/waveline.vsh", frag: "./shader/waveline.fsh");
+        vo(glrs::BoxedF32VO::new((2 * channels + 1) * fft_config.size));
+        fn new(fft_config: FftConfig, channels: usize) {
+            Self {
+                shaders, vo,
+                fft_config,
+                channels,
                 wave_x_off: 0,
                 wave_x_off_f: 0.0,
+                period: fft_config.size as f32,
             }
         };
 
+        fft_config: FftConfig,
+        channels: usize,
         wave_x_off: i32,
         wave_x_off_f: f32,
+        // trigger period in half samples
+        period: f32,
     }
 }
 impl RenderWaveline {
     pub fn render(&self) {
+        let fft_size = self.fft_config.size as i32;
         self.bind();
-        glrs::uniform(1, V1F(self.wave_x_off as f32 / FFT_SIZE as f32));
-        for j in 0..2 {
+        glrs::uniform(1, V1F(self.wave_x_off as f32 / fft_size as f32));
+        for j in 0..self.channels as i32 {
             glrs::DrawArrays::LineStrip {
-                range: (j * FFT_SIZE as i32)..((j + 1) * FFT_SIZE as i32),
+                range: (j * fft_size)..((j + 1) * fft_size),
                 line_width: 1.0,
                 point_size: 1.0,
             }
@@ -256,39 +1373,44 @@ impl RenderWaveline {
     }
     pub fn set_wave(
         &mut self,
-        wave: &(
-            [[Complex32; FFT_SIZE]; 2],
-            [[f32; FFT_SIZE]; 2],
-            [[f32; HALF_FFT_SIZE]; 2],
-        ),
-        _wave_last: &[[f32; FFT_SIZE]; 2],
+        wave: &AudioDataChunk,
+        _wave_last: &[Vec<f32>],
         sample_rate: f32,
+        mapping: FrequencyMapping,
     ) {
-        for i in 0..FFT_SIZE {
-            let k = i as f32 / FFT_SIZE as f32;
+        let FftConfig {
+            size: fft_size,
+            stride: fft_stride,
+        } = self.fft_config;
+        let channels = self.channels;
+        // x/y scope of the first two channels; a mono chunk plots against itself
+        let (scope_x, scope_y) = (0, 1.min(channels - 1));
+        for i in 0..fft_size {
+            let k = i as f32 / fft_size as f32;
             let x = k * 2.0 - 1.0;
-            for ch in 0..2 {
-                self.vo.data[i + ch * FFT_SIZE] = [x, wave.1[ch][i]];
-                self.vo.data[i + (ch + 2) * FFT_SIZE] = [
-                    ((k + 0.5 / FFT_SIZE as f32).ln() * 0.2 + 1.0) * 2.0 - 1.0,
+            for ch in 0..channels {
+                self.vo.data[i + ch * fft_size] = [x, wave.wave[ch][i]];
+                // bin i / 2, across the display like the spectrogram's frequency axis
+                let freq = (k + 0.5 / fft_size as f32) * sample_rate / 2.0;
+                self.vo.data[i + (ch + channels) * fft_size] = [
+                    mapping.to_display(freq) * 2.0 - 1.0,
                     // k,
-                    wave.0[ch][i / 2].abs().log10() / 5.0,
+                    (20.0 * wave.spectrum[ch][i / 2].abs().log10() + 60.0) / 100.0,
                 ];
             }
-            self.vo.data[i + 4 * FFT_SIZE] = [wave.1[0][i], wave.1[1][i]];
+            self.vo.data[i + 2 * channels * fft_size] =
+                [wave.wave[scope_x][i], wave.wave[scope_y][i]];
         }
         {
-            let (max_i, _) = wave.0[0][..HALF_FFT_SIZE]
-                .iter()
-                .copied()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
-                .unwrap();
-            let freq_reassigned = wave.2[0][max_i];
-            let period = (sample_rate / freq_reassigned * 2.0).min((HALF_FFT_SIZE) as f32);
-
-            self.wave_x_off_f -= FFT_STRIDE as f32;
-            self.wave_x_off_f -= period * (self.wave_x_off_f / period).round();
+            // offsets are in half samples, as the wave spans two units of clip space. Each hop
+            // moves the wave back by a stride; moving it forward again, wrapped to whole
+            // periods of the first channel's fundamental, keeps a periodic wave in place. The
+            // last period is held through hops without a confident pitch.
+            if let Some(pitch) = wave.pitch[0].filter(|it| it.confidence > TRIGGER_CONFIDENCE) {
+                self.period = (sample_rate / pitch.freq * 2.0).min(fft_size as f32);
+            }
+            self.wave_x_off_f += 2.0 * fft_stride as f32;
+            self.wave_x_off_f -= self.period * (self.wave_x_off_f / self.period).round();
             self.wave_x_off = self.wave_x_off_f as i32;
         }
         self.vo.update();
@@ -316,3 +1438,108 @@ impl RenderFloatingIndicator {
         glrs::TransparencyMode::Normal.apply();
     }
 }
+
+/// Most glyphs (plus one backing box per label) a [`RenderText`] holds at once.
+const MAX_TEXT_QUADS: usize = 4096;
+glrs_renderable! {
+    pub RenderText(glrs::BoxedF32VO<4>) {
+        shaders(vert: "./shader/text.vsh", frag: "./shader/text.fsh");
+        vo(glrs::BoxedF32VO::new(MAX_TEXT_QUADS * 6));
+        fn new() {
+            let (width, height, pixels) = font::atlas();
+            let font = glrs::GLTexture2d::new(width, height);
+            font.set_filter(glrs::GLTextureFilter::Nearest);
+            font.update_partial(0, 0, width, &pixels);
+            Self {
+                vo, shaders,
+                font,
+                labels: vec![],
+                len: 0,
+            }
+        };
+
+        font: glrs::GLTexture2d,
+        // vertex range and colour of each label
+        labels: Vec<(Range<usize>, glrs::Rgba<f32>)>,
+        len: usize,
+    }
+}
+impl RenderText {
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.len = 0;
+    }
+    /// Lay out `text` with its top-left corner `pos` pixels from the window's top-left, at
+    /// `scale` pixels per font pixel, on a dark backing box. Call [`Self::update`] once done.
+    pub fn push(&mut self, text: &str, pos: (f32, f32), scale: f32, color: glrs::Rgba<f32>) {
+        let start = self.len;
+        let (width, height) = font::text_size(text);
+        let pad = 2.0 * scale;
+        self.push_quad(
+            [
+                pos.0 - pad,
+                pos.1 - pad,
+                pos.0 + width as f32 * scale + pad,
+                pos.1 + height as f32 * scale,
+            ],
+            [-1.0; 4],
+        );
+        let atlas_width = (font::NUM_GLYPHS * font::ADVANCE) as f32;
+        for (row, line) in text.lines().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let x = pos.0 + (column * font::ADVANCE) as f32 * scale;
+                let y = pos.1 + (row * font::LINE_HEIGHT) as f32 * scale;
+                let u = (font::glyph_index(c) * font::ADVANCE) as f32;
+                self.push_quad(
+                    [
+                        x,
+                        y,
+                        x + font::GLYPH_WIDTH as f32 * scale,
+                        y + font::GLYPH_HEIGHT as f32 * scale,
+                    ],
+                    [
+                        u / atlas_width,
+                        0.0,
+                        (u + font::GLYPH_WIDTH as f32) / atlas_width,
+                        1.0,
+                    ],
+                );
+            }
+        }
+        self.labels.push((start..self.len, color));
+    }
+    /// Two triangles covering `[x0, y0, x1, y1]`, textured with `[u0, v0, u1, v1]`. Dropped
+    /// once the buffer is full.
+    fn push_quad(&mut self, [x0, y0, x1, y1]: [f32; 4], [u0, v0, u1, v1]: [f32; 4]) {
+        if self.len + 6 > self.vo.len() {
+            return;
+        }
+        self.vo.data[self.len..self.len + 6].copy_from_slice(&[
+            [x0, y0, u0, v0],
+            [x1, y0, u1, v0],
+            [x0, y1, u0, v1],
+            [x1, y0, u1, v0],
+            [x1, y1, u1, v1],
+            [x0, y1, u0, v1],
+        ]);
+        self.len += 6;
+    }
+    /// Upload everything pushed since the last [`Self::clear`].
+    pub fn update(&self) {
+        self.vo.update_range(0..self.len);
+    }
+    pub fn render(&self, opacity: f32, winfo: &glfwrs::Winfo) {
+        self.bind();
+        self.font.bind(glrs::GLTextureSlot::Tex0, 2);
+        let Vec2I(width, height) = winfo.bounds.dim;
+        glrs::uniform(1, V2F(width as f32, height as f32));
+        glrs::uniform(4, V1F(opacity));
+        for (range, color) in &self.labels {
+            glrs::uniform(3, V4F(*color));
+            glrs::DrawArrays::Triangles {
+                range: (range.start / 3) as i32..(range.end / 3) as i32,
+            }
+            .exec();
+        }
+    }
+}
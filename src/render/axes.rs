@@ -0,0 +1,297 @@
+use crate::{glrs_renderable, util::Vec2I};
+
+use super::{
+    color::ColorLut,
+    font, glfwrs,
+    glrs::{self, GLParam::*},
+    ChannelLayout, FrequencyMapping, FrequencyScale, RenderText, SPECTROGRAM_DISPLAY_VERTS,
+};
+
+/// Most tick marks [`RenderTicks`] holds at once.
+const MAX_TICKS: usize = 1024;
+/// Tick lengths in pixels.
+const MAJOR_TICK: f32 = 8.0;
+const MINOR_TICK: f32 = 4.0;
+/// Aim for about this many labelled ticks across an evenly spaced axis.
+const TARGET_TICKS: f32 = 8.0;
+/// Closest two ticks, and two labels, of the frequency axis are drawn, in pixels. Scales
+/// other than log squeeze some decades together.
+const MIN_TICK_SPACING: f32 = 3.0;
+const MIN_LABEL_SPACING: f32 = (font::LINE_HEIGHT + 2) as f32;
+/// Spacing between labelled dB levels on the legend.
+const LEGEND_DB_STEP: f32 = 10.0;
+/// Size of the legend's colour bar, and its distance from the window's right and bottom
+/// edges, in pixels. It sits inside the frequency axis and above the time axis.
+const LEGEND_WIDTH: f32 = 8.0;
+const LEGEND_HEIGHT: f32 = 120.0;
+const LEGEND_MARGIN: (f32, f32) = (64.0, 32.0);
+const AXIS_COLOR: glrs::Rgba<f32> = glrs::Rgba {
+    r: 0.85,
+    g: 0.85,
+    b: 0.85,
+    a: 0.8,
+};
+
+/// Frequency axis along the right edge of each channel's band, time axis along the bottom
+/// and a legend of the dB colour scale, laid out in pixels for one window size.
+pub struct Axes {
+    ticks: RenderTicks,
+    labels: RenderText,
+    legend: RenderLegend,
+    /// Left, top, right and bottom edges of the legend's colour bar.
+    legend_rect: [f32; 4],
+}
+impl Axes {
+    pub fn new() -> Self {
+        Self {
+            ticks: RenderTicks::new(),
+            labels: RenderText::new(),
+            legend: RenderLegend::new(),
+            legend_rect: [0.0; 4],
+        }
+    }
+
+    /// Lay everything out for a `viewport` sized window, frequencies spread over each band by
+    /// `mapping` and the history shown from `time_range.0` seconds ago at the right edge to
+    /// `time_range.1` seconds ago at the left, and the legend from `db_range.0` to
+    /// `db_range.1` dBFS.
+    pub fn layout(
+        &mut self,
+        viewport: Vec2I,
+        channels: usize,
+        channel_layout: ChannelLayout,
+        mapping: FrequencyMapping,
+        time_range: (f32, f32),
+        db_range: (f32, f32),
+    ) {
+        let (width, height) = (viewport.0 as f32, viewport.1 as f32);
+        self.ticks.clear();
+        self.labels.clear();
+
+        // frequency axis: ticks pointing in from the right edge, labels inside them
+        let (lo, hi) = (mapping.min_freq, mapping.max_freq);
+        let linear_step = match mapping.scale {
+            FrequencyScale::Linear => Some(nice_step((hi - lo) / TARGET_TICKS)),
+            _ => None,
+        };
+        let ticks = match linear_step {
+            Some(step) => linear_ticks(lo, hi, step),
+            None => decade_ticks(lo, hi),
+        };
+        for j in 0..channel_layout.bands(channels) {
+            let (band_lo, band_height) = channel_layout.band(j, channels);
+            let (mut last_tick, mut last_label) = (f32::INFINITY, f32::INFINITY);
+            for &(freq, major) in &ticks {
+                let y = mapping.to_display(freq);
+                if !(0.0..=1.0).contains(&y) {
+                    continue;
+                }
+                let y = (1.0 - (band_lo + band_height * y)) * height;
+                if last_tick - y < MIN_TICK_SPACING {
+                    continue;
+                }
+                last_tick = y;
+                let label = major && last_label - y >= MIN_LABEL_SPACING;
+                let length = if label { MAJOR_TICK } else { MINOR_TICK };
+                self.ticks.push([width - length, y - 0.5, width, y + 0.5]);
+                if label {
+                    let text = format_freq(freq, linear_step.unwrap_or(freq));
+                    let (text_width, text_height) = font::text_size(&text);
+                    let x = width - MAJOR_TICK - 4.0 - text_width as f32;
+                    let text_y = y - text_height as f32 / 2.0;
+                    // keep labels within the band
+                    let top = (1.0 - band_lo - band_height) * height;
+                    let bottom = (1.0 - band_lo) * height - text_height as f32;
+                    if (top..=bottom).contains(&text_y) {
+                        self.labels.push(&text, (x, text_y), 1.0, AXIS_COLOR);
+                        last_label = y;
+                    }
+                }
+            }
+        }
+
+        // time axis: seconds before now, or before pausing
+        let (newest, oldest) = time_range;
+        if oldest > newest {
+            let step = nice_step((oldest - newest) / TARGET_TICKS);
+            let decimals = decimals(step);
+            // no tick at the present, which is the right edge itself
+            for k in ((newest / step).ceil() as i64).max(1).. {
+                let seconds = k as f32 * step;
+                if seconds > oldest {
+                    break;
+                }
+                let x = (oldest - seconds) / (oldest - newest) * width;
+                self.ticks
+                    .push([x - 0.5, height - MAJOR_TICK, x + 0.5, height]);
+                let text = format!("-{seconds:.decimals$} s");
+                let (text_width, text_height) = font::text_size(&text);
+                let x = x - text_width as f32 / 2.0;
+                let y = height - MAJOR_TICK - 4.0 - text_height as f32;
+                if x >= 0.0 {
+                    self.labels.push(&text, (x, y), 1.0, AXIS_COLOR);
+                }
+            }
+        }
+
+        // legend: the colour bar with dB levels to its left
+        let right = width - LEGEND_MARGIN.0;
+        let bottom = height - LEGEND_MARGIN.1;
+        self.legend_rect = [right - LEGEND_WIDTH, bottom - LEGEND_HEIGHT, right, bottom];
+        let (floor, ceil) = db_range;
+        let mut db = (floor / LEGEND_DB_STEP).ceil() * LEGEND_DB_STEP;
+        while db <= ceil {
+            let y = bottom - LEGEND_HEIGHT * (db - floor) / (ceil - floor);
+            let left = right - LEGEND_WIDTH;
+            self.ticks.push([left - MINOR_TICK, y - 0.5, left, y + 0.5]);
+            let text = format!("{db} dB");
+            let (text_width, text_height) = font::text_size(&text);
+            let x = left - MINOR_TICK - 4.0 - text_width as f32;
+            self.labels
+                .push(&text, (x, y - text_height as f32 / 2.0), 1.0, AXIS_COLOR);
+            db += LEGEND_DB_STEP;
+        }
+
+        self.ticks.update();
+        self.labels.update();
+    }
+
+    pub fn render(&self, color_lut: &ColorLut, winfo: &glfwrs::Winfo) {
+        self.legend.render(self.legend_rect, color_lut, winfo);
+        self.ticks.render(AXIS_COLOR, winfo);
+        self.labels.render(1.0, winfo);
+    }
+}
+
+/// Ticks from `lo` to `hi` Hz as frequency and whether the tick is labelled, for the
+/// scales that aren't linear: labels at 1, 2 and 5 of every decade, unlabelled ticks at the
+/// other multiples.
+fn decade_ticks(lo: f32, hi: f32) -> Vec<(f32, bool)> {
+    let mut ticks = vec![];
+    let mut decade = 10f32.powf(lo.max(1.0).log10().floor());
+    while decade <= hi {
+        for multiple in 1..10 {
+            let freq = decade * multiple as f32;
+            if (lo..=hi).contains(&freq) {
+                ticks.push((freq, matches!(multiple, 1 | 2 | 5)));
+            }
+        }
+        decade *= 10.0;
+    }
+    ticks
+}
+
+/// Labelled ticks at the multiples of `step` from `lo` to `hi`.
+fn linear_ticks(lo: f32, hi: f32, step: f32) -> Vec<(f32, bool)> {
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|k| (k as f32 * step, true)).collect()
+}
+
+/// The smallest of 1, 2 or 5 times a power of ten at least `rough`.
+fn nice_step(rough: f32) -> f32 {
+    let magnitude = 10f32.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|it| it * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Decimals needed to tell apart values `step` apart.
+fn decimals(step: f32) -> usize {
+    (-step.log10().floor()).max(0.0) as usize
+}
+
+/// `freq` in Hz or kHz, with as many decimals as ticks `step` Hz apart need.
+fn format_freq(freq: f32, step: f32) -> String {
+    if freq >= 1000.0 {
+        let decimals = decimals(step / 1000.0);
+        format!("{:.*} kHz", decimals, freq / 1000.0)
+    } else {
+        let decimals = decimals(step);
+        format!("{:.*} Hz", decimals, freq)
+    }
+}
+
+glrs_renderable! {
+    pub RenderTicks(glrs::BoxedF32VO<2>) {
+        shaders(vert: "../shader/ticks.vsh", frag: "../shader/ticks.fsh");
+        vo(glrs::BoxedF32VO::new(MAX_TICKS * 6));
+        fn new() {
+            Self {
+                vo, shaders,
+                len: 0,
+            }
+        };
+
+        len: usize,
+    }
+}
+impl RenderTicks {
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+    /// A filled rectangle `[x0, y0, x1, y1]` in pixels from the window's top-left. Dropped
+    /// once the buffer is full.
+    pub fn push(&mut self, [x0, y0, x1, y1]: [f32; 4]) {
+        if self.len + 6 > self.vo.len() {
+            return;
+        }
+        self.vo.data[self.len..self.len + 6].copy_from_slice(&[
+            [x0, y0],
+            [x1, y0],
+            [x0, y1],
+            [x1, y0],
+            [x1, y1],
+            [x0, y1],
+        ]);
+        self.len += 6;
+    }
+    /// Upload everything pushed since the last [`Self::clear`].
+    pub fn update(&self) {
+        self.vo.update_range(0..self.len);
+    }
+    pub fn render(&self, color: glrs::Rgba<f32>, winfo: &glfwrs::Winfo) {
+        self.bind();
+        let Vec2I(width, height) = winfo.bounds.dim;
+        glrs::uniform(1, V2F(width as f32, height as f32));
+        glrs::uniform(3, V4F(color));
+        glrs::DrawArrays::Triangles {
+            range: 0..(self.len / 3) as i32,
+        }
+        .exec();
+    }
+}
+
+glrs_renderable! {
+    pub RenderLegend(glrs::TriPosVO<2>) {
+        shaders(vert: "../shader/legend.vsh", frag: "../shader/legend.fsh");
+        vo(glrs::TriPosVO::new(SPECTROGRAM_DISPLAY_VERTS));
+        fn new() {
+            Self {
+                shaders, vo,
+            }
+        };
+    }
+}
+impl RenderLegend {
+    /// Draw the colour scale from floor to ceiling, bottom to top, over `rect` (left, top,
+    /// right, bottom in pixels from the window's top-left).
+    pub fn render(&self, rect: [f32; 4], color_lut: &ColorLut, winfo: &glfwrs::Winfo) {
+        self.bind();
+        color_lut.set_uniforms();
+        let Vec2I(width, height) = winfo.bounds.dim;
+        glrs::uniform(1, V2F(width as f32, height as f32));
+        glrs::uniform(
+            2,
+            V4F(glrs::Rgba {
+                r: rect[0],
+                g: rect[1],
+                b: rect[2],
+                a: rect[3],
+            }),
+        );
+        glrs::DrawArrays::Triangles { range: 0..2 }.exec();
+    }
+}
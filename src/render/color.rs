@@ -0,0 +1,316 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::{
+    glrs::{self, GLParam::*},
+    level_to_db,
+};
+
+/// Entries in a colormap's lookup texture.
+const LUT_SIZE: usize = 256;
+/// How fast auto-gain lets the ceiling fall after a peak, and the floor follow the noise,
+/// as time constants in seconds.
+const PEAK_RELEASE_SECONDS: f32 = 2.0;
+const FLOOR_SECONDS: f32 = 2.0;
+/// Steps of the floor, ceiling and gamma controls.
+const DB_STEP: f32 = 3.0;
+const GAMMA_STEP: f32 = 1.25;
+/// Auto-gain puts the floor this far above the noise, so the noise stays dark, and keeps
+/// at least this range between floor and ceiling.
+const FLOOR_MARGIN_DB: f32 = 6.0;
+const MIN_AUTO_RANGE_DB: f32 = 24.0;
+
+/// Colours the spectrogram is drawn in, from quiet to loud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Dark red through purple and green to white, the original colours.
+    #[default]
+    Heat,
+    /// Blue through green and yellow to red, like the old texture spectrogram.
+    Rainbow,
+    Viridis,
+    Magma,
+    Inferno,
+    Grayscale,
+}
+impl Colormap {
+    const ALL: [Self; 6] = [
+        Self::Heat,
+        Self::Rainbow,
+        Self::Viridis,
+        Self::Magma,
+        Self::Inferno,
+        Self::Grayscale,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Heat => "heat",
+            Self::Rainbow => "rainbow",
+            Self::Viridis => "viridis",
+            Self::Magma => "magma",
+            Self::Inferno => "inferno",
+            Self::Grayscale => "grayscale",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Heat => Self::Rainbow,
+            Self::Rainbow => Self::Viridis,
+            Self::Viridis => Self::Magma,
+            Self::Magma => Self::Inferno,
+            Self::Inferno => Self::Grayscale,
+            Self::Grayscale => Self::Heat,
+        }
+    }
+    /// The colour at `x`, from 0 (quietest) to 1 (loudest).
+    fn color(&self, x: f32) -> [f32; 3] {
+        let k = x.clamp(0.0, 1.0);
+        match self {
+            Self::Heat => {
+                let low = 0.15 * (k * 3.0).min(1.0);
+                [
+                    0.9 * k.powi(5) + low,
+                    0.9 * k.powi(3) + 0.3 * low,
+                    1.8 * k * k + 0.2 * low,
+                ]
+            }
+            Self::Rainbow => hsv_to_rgb(
+                (1.6 - 0.75 * k.powi(4)).rem_euclid(1.0),
+                1.0 - k.powi(7),
+                k.powf(0.7),
+            ),
+            // polynomial fits of matplotlib's maps, by Matt Zucker (CC0)
+            Self::Viridis => polynomial(
+                k,
+                [
+                    [0.27772733, 0.00540734, 0.33409981],
+                    [0.10509304, 1.40461353, 1.38459016],
+                    [-0.33086183, 0.21484756, 0.09509516],
+                    [-4.63423050, -5.79910097, -19.33244096],
+                    [6.22826994, 14.17993337, 56.69055260],
+                    [4.77638500, -13.74514538, -65.35303263],
+                    [-5.43545586, 4.64585261, 26.31243525],
+                ],
+            ),
+            Self::Magma => polynomial(
+                k,
+                [
+                    [-0.00213649, -0.00074966, -0.00538613],
+                    [0.25166054, 0.67752324, 2.49402660],
+                    [8.35371728, -3.57771951, 0.31446790],
+                    [-27.66873309, 14.26473078, -13.64921319],
+                    [52.17613981, -27.94360607, 12.94416944],
+                    [-50.76852536, 29.04658282, 4.23415299],
+                    [18.65570507, -11.48977352, -5.60196151],
+                ],
+            ),
+            Self::Inferno => polynomial(
+                k,
+                [
+                    [0.00021894, 0.00165100, -0.01948090],
+                    [0.10651342, 0.56395644, 3.93271239],
+                    [11.60249308, -3.97285397, -15.94239411],
+                    [-41.70399613, 17.43639888, 44.35414520],
+                    [77.16293570, -33.40235894, -81.80730926],
+                    [-71.31942824, 32.62606426, 73.20951986],
+                    [25.13112622, -12.24266895, -23.07032500],
+                ],
+            ),
+            Self::Grayscale => [k; 3],
+        }
+    }
+    /// [`LUT_SIZE`] colours evenly spread from 0 to 1.
+    fn lut(&self) -> Vec<glrs::Rgba<u8>> {
+        (0..LUT_SIZE)
+            .map(|i| {
+                let [r, g, b] = self
+                    .color(i as f32 / (LUT_SIZE - 1) as f32)
+                    .map(|it| (it.clamp(0.0, 1.0) * 255.0).round() as u8);
+                glrs::Rgba { r, g, b, a: 255 }
+            })
+            .collect()
+    }
+}
+
+/// `c[0] + c[1] x + c[2] x² + ...` for each of red, green and blue, in double precision as
+/// the terms mostly cancel out.
+fn polynomial(x: f32, c: [[f64; 3]; 7]) -> [f32; 3] {
+    let x = x as f64;
+    std::array::from_fn(|i| c.iter().rev().fold(0.0, |acc, it| acc * x + it[i]) as f32)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    [1.0, 2.0 / 3.0, 1.0 / 3.0].map(|k| {
+        let p = ((h + k).fract() * 6.0 - 3.0).abs();
+        v * (1.0 + s * ((p - 1.0).clamp(0.0, 1.0) - 1.0))
+    })
+}
+
+#[derive(Debug)]
+pub struct ParseColormapError(String);
+impl Display for ParseColormapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown colormap {:?}, expected heat, rainbow, viridis, magma, inferno or grayscale",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseColormapError {}
+
+impl FromStr for Colormap {
+    type Err = ParseColormapError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name() == s)
+            .ok_or_else(|| ParseColormapError(s.to_string()))
+    }
+}
+
+/// How levels are coloured: the colormap, the levels at its ends and the curve between
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorScale {
+    pub colormap: Colormap,
+    /// dBFS drawn in the colormap's first and last colour. Magnitudes are normalized so a
+    /// full scale sinusoid reads 0 dBFS.
+    pub db_floor: f32,
+    pub db_ceil: f32,
+    /// Exponent applied between floor and ceiling: below 1 brings out quiet detail, above 1
+    /// leaves only the loudest parts bright.
+    pub gamma: f32,
+    /// Follow the signal's recent peak and noise floor instead of `db_floor` and `db_ceil`.
+    pub auto_gain: bool,
+}
+impl Default for ColorScale {
+    fn default() -> Self {
+        Self {
+            colormap: Colormap::default(),
+            db_floor: -60.0,
+            db_ceil: -16.0,
+            gamma: 1.0,
+            auto_gain: false,
+        }
+    }
+}
+
+impl ColorScale {
+    /// Raise or lower the floor a step, keeping it below the ceiling.
+    pub fn step_floor(self, up: bool) -> Self {
+        let db_floor = self.db_floor + if up { DB_STEP } else { -DB_STEP };
+        Self {
+            db_floor: db_floor.min(self.db_ceil - DB_STEP),
+            ..self
+        }
+    }
+    /// Raise or lower the ceiling a step, keeping it above the floor.
+    pub fn step_ceil(self, up: bool) -> Self {
+        let db_ceil = self.db_ceil + if up { DB_STEP } else { -DB_STEP };
+        Self {
+            db_ceil: db_ceil.max(self.db_floor + DB_STEP),
+            ..self
+        }
+    }
+    /// Raise or lower gamma a step.
+    pub fn step_gamma(self, up: bool) -> Self {
+        let factor = if up { GAMMA_STEP } else { 1.0 / GAMMA_STEP };
+        Self {
+            gamma: (self.gamma * factor).clamp(0.1, 10.0),
+            ..self
+        }
+    }
+}
+
+/// The colour scale as the spectrogram shaders see it, through `color_scale.glsl`: the
+/// colormap as a lookup texture, and the floor, ceiling and gamma in use.
+pub struct ColorLut {
+    texture: glrs::GLTexture2d,
+    colormap: Colormap,
+    db_range: (f32, f32),
+    gamma: f32,
+}
+impl ColorLut {
+    pub fn new(scale: ColorScale) -> Self {
+        // one row; GL has 1D textures, but nothing else here uses them
+        let texture = glrs::GLTexture2d::new(LUT_SIZE, 1);
+        texture.update_partial(0, 0, LUT_SIZE, &scale.colormap.lut());
+        Self {
+            texture,
+            colormap: scale.colormap,
+            db_range: (scale.db_floor, scale.db_ceil),
+            gamma: scale.gamma,
+        }
+    }
+    /// Use `scale`, drawing between `db_range` (floor, ceiling) rather than its own levels
+    /// when given, as for auto-gain.
+    pub fn set(&mut self, scale: ColorScale, db_range: Option<(f32, f32)>) {
+        if scale.colormap != self.colormap {
+            self.colormap = scale.colormap;
+            self.texture
+                .update_partial(0, 0, LUT_SIZE, &scale.colormap.lut());
+        }
+        self.db_range = db_range.unwrap_or((scale.db_floor, scale.db_ceil));
+        self.gamma = scale.gamma;
+    }
+    /// Floor and ceiling in use, in dBFS.
+    pub fn db_range(&self) -> (f32, f32) {
+        self.db_range
+    }
+    /// Bind the lookup texture and set the uniforms, for the bound shader.
+    pub fn set_uniforms(&self) {
+        self.texture.bind(glrs::GLTextureSlot::Tex1, 12);
+        glrs::uniform(13, V2F(self.db_range.0, self.db_range.1));
+        glrs::uniform(14, V1F(self.gamma));
+    }
+}
+
+/// Follows the display's recent peak and noise floor, for a colour scale that adapts to
+/// the signal.
+#[derive(Default)]
+pub struct AutoGain {
+    /// Smoothed peak and noise floor in dBFS, once anything but silence has been seen.
+    levels: Option<(f32, f32)>,
+    scratch: Vec<u16>,
+}
+impl AutoGain {
+    /// Take in a frame, as packed levels per display row of each channel, `seconds` after
+    /// the last.
+    pub fn push<'a>(&mut self, columns: impl IntoIterator<Item = &'a [u16]>, seconds: f32) {
+        self.scratch.clear();
+        for column in columns {
+            // empty rows are gaps between the reassigned spectrum's points, not quiet
+            self.scratch.extend(column.iter().filter(|&&it| it > 0));
+        }
+        if self.scratch.is_empty() {
+            return;
+        }
+        let middle = self.scratch.len() / 2;
+        let (_, &mut median, _) = self.scratch.select_nth_unstable(middle);
+        let peak = level_to_db(self.scratch.iter().copied().max().unwrap_or(0));
+        let noise = level_to_db(median);
+        self.levels = Some(match self.levels {
+            None => (peak, noise),
+            Some((last_peak, last_noise)) => {
+                let follow = |last: f32, new: f32, tau: f32| {
+                    last + (new - last) * (1.0 - (-seconds / tau).exp())
+                };
+                (
+                    if peak > last_peak {
+                        peak
+                    } else {
+                        follow(last_peak, peak, PEAK_RELEASE_SECONDS)
+                    },
+                    follow(last_noise, noise, FLOOR_SECONDS),
+                )
+            }
+        });
+    }
+    /// Floor and ceiling in dBFS fitting what has been seen, if anything has.
+    pub fn db_range(&self) -> Option<(f32, f32)> {
+        let (peak, noise) = self.levels?;
+        let floor = (noise + FLOOR_MARGIN_DB).min(peak - MIN_AUTO_RANGE_DB);
+        Some((floor, peak))
+    }
+}
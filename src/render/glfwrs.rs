@@ -53,6 +53,12 @@ impl Window {
         }
     }
 
+    /// An invisible window, only for its GL context (offscreen rendering).
+    pub fn new_hidden(glfw: &mut glfw::Glfw) -> Window {
+        glfw.window_hint(glfw::WindowHint::Visible(false));
+        Self::new(glfw, Vec2I(1, 1), false, false, "spexia", |_| {})
+    }
+
     pub fn render<F: Fn(Winfo)>(&mut self, render: F) {
         //////////////////////////////////////////
         self.winfo = Winfo {
@@ -108,6 +114,11 @@ impl Window {
         self.winfo = winfo;
     }
 
+    /// Size in pixels as of the last [`Self::render`] or event.
+    pub fn size(&self) -> Vec2I {
+        self.winfo.bounds.dim
+    }
+
     pub fn should_close(&self) -> bool {
         self.window.should_close()
     }
@@ -32,6 +32,7 @@ impl GLShaderType {
 pub enum GLError {
     ShaderCompilation { typ: GLShaderType, message: String },
     ShaderLinking { message: String },
+    Framebuffer { message: String },
 }
 impl Display for GLError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -41,6 +42,7 @@ impl Display for GLError {
                 write!(f, "Shader/{} compile failed:\n{}", typ.name(), message)
             }
             GLError::ShaderLinking { message } => write!(f, "Shader link failed:\n{}", message),
+            GLError::Framebuffer { message } => write!(f, "Framebuffer incomplete: {}", message),
         }
     }
 }
@@ -195,33 +197,35 @@ impl Rgba<f32> {
     }
 }
 
-pub struct GLTexture2d<const W: usize, const H: usize> {
+pub struct GLTexture2d {
     ref_id: GLuint,
+    width: usize,
+    height: usize,
 }
-impl<const W: usize, const H: usize> GLTexture2d<W, H> {
-    pub fn new() -> Self {
-        let data: Box<[[Rgba<u8>; W]; H]> = vec![
-            [Rgba {
+impl GLTexture2d {
+    pub fn new(width: usize, height: usize) -> Self {
+        let data = vec![
+            Rgba {
                 r: 0,
                 g: 0,
                 b: 0,
                 a: 0,
-            }; W];
-            H
-        ]
-        .try_into()
-        .unwrap();
+            };
+            width * height
+        ];
 
         let mut ref_id = 0;
 
         unsafe {
             gl::GenTextures(1, &mut ref_id);
-            gl::BindTexture(gl::TEXTURE_2D, ref_id);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
         }
 
-        let this = Self { ref_id };
+        let this = Self {
+            ref_id,
+            width,
+            height,
+        };
+        this.set_filter(GLTextureFilter::Linear);
         unsafe {
             this.gl_tex_image_2d(data.as_ptr() as *const GLvoid);
         }
@@ -232,14 +236,21 @@ impl<const W: usize, const H: usize> GLTexture2d<W, H> {
             gl::TEXTURE_2D,
             0,
             gl::RGBA as GLint,
-            W as GLint,
-            H as GLint,
+            self.width as GLint,
+            self.height as GLint,
             0,
             gl::RGBA,
             gl::UNSIGNED_BYTE,
             pixels,
         );
     }
+    pub fn set_filter(&self, filter: GLTextureFilter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.ref_id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter.gl_int());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter.gl_int());
+        }
+    }
     pub fn bind(&self, slot: GLTextureSlot, location: GLint) {
         unsafe {
             gl::ActiveTexture(slot.gl_enum());
@@ -247,12 +258,11 @@ impl<const W: usize, const H: usize> GLTexture2d<W, H> {
             gl::Uniform1i(location, slot.gl_int());
         }
     }
-    pub fn update_partial<const WS: usize, const HS: usize>(
-        &self,
-        x0: usize,
-        y0: usize,
-        data: [[Rgba<u8>; WS]; HS],
-    ) {
+    /// Overwrite a `width` by `data.len() / width` region starting at (`x0`, `y0`), row-major.
+    pub fn update_partial(&self, x0: usize, y0: usize, width: usize, data: &[Rgba<u8>]) {
+        assert_eq!(data.len() % width, 0);
+        let height = data.len() / width;
+        assert!(x0 + width <= self.width && y0 + height <= self.height);
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, self.ref_id);
             gl::TexSubImage2D(
@@ -260,8 +270,8 @@ impl<const W: usize, const H: usize> GLTexture2d<W, H> {
                 0,
                 x0 as GLint,
                 y0 as GLint,
-                WS as GLint,
-                HS as GLint,
+                width as GLint,
+                height as GLint,
                 gl::RGBA,
                 gl::UNSIGNED_BYTE,
                 data.as_ptr() as *const GLvoid,
@@ -269,23 +279,116 @@ impl<const W: usize, const H: usize> GLTexture2d<W, H> {
         }
     }
 }
-impl<const W: usize, const H: usize> Drop for GLTexture2d<W, H> {
+impl Drop for GLTexture2d {
     fn drop(&mut self) {
         unsafe { gl::DeleteTextures(1, &self.ref_id) }
     }
 }
+/// An offscreen RGBA render target, for drawing without a visible window.
+pub struct GLFramebuffer {
+    ref_id: GLuint,
+    texture: GLTexture2d,
+}
+impl GLFramebuffer {
+    pub fn new(width: usize, height: usize) -> Result<Self, GLError> {
+        let mut max_size: GLint = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size) };
+        if width.max(height) > max_size as usize {
+            return Err(GLError::Framebuffer {
+                message: format!(
+                    "{}x{} exceeds the maximum size of {}",
+                    width, height, max_size
+                ),
+            });
+        }
+
+        let texture = GLTexture2d::new(width, height);
+        let mut ref_id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut ref_id);
+        }
+        let this = Self { ref_id, texture };
+        this.bind();
+        let status = unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                this.texture.ref_id,
+                0,
+            );
+            gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(GLError::Framebuffer {
+                message: format!("status {:#x}", status),
+            });
+        }
+        Ok(this)
+    }
+    /// Direct drawing to this framebuffer instead of the window.
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.ref_id) }
+    }
+    /// Read back the whole framebuffer, top row first.
+    pub fn read_pixels(&self) -> Vec<Rgba<u8>> {
+        let (width, height) = (self.texture.width, self.texture.height);
+        let mut pixels = vec![Rgba::default(); width * height];
+        self.bind();
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as GLint,
+                height as GLint,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut GLvoid,
+            );
+        }
+        // gl reads bottom row first
+        let rows: Vec<&[Rgba<u8>]> = pixels.chunks_exact(width).rev().collect();
+        rows.concat()
+    }
+}
+impl Drop for GLFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &self.ref_id);
+        }
+    }
+}
+
+pub enum GLTextureFilter {
+    Linear,
+    /// Keeps texels sharp, e.g. for pixel fonts.
+    Nearest,
+}
+impl GLTextureFilter {
+    fn gl_int(&self) -> GLint {
+        match self {
+            Self::Linear => gl::LINEAR as GLint,
+            Self::Nearest => gl::NEAREST as GLint,
+        }
+    }
+}
 pub enum GLTextureSlot {
     Tex0,
+    Tex1,
 }
 impl GLTextureSlot {
     fn gl_enum(&self) -> GLenum {
         match self {
             Self::Tex0 => gl::TEXTURE0,
+            Self::Tex1 => gl::TEXTURE1,
         }
     }
     fn gl_int(&self) -> GLint {
         match self {
             Self::Tex0 => 0,
+            Self::Tex1 => 1,
         }
     }
 }
@@ -349,18 +452,18 @@ impl<const N: usize> Drop for TriPosVO<N> {
     }
 }
 
-/// A vertex object containing `N` `S` dimensional [`f32`] vectors
-pub struct F32VO<const N: usize, const S: usize> {
+/// A vertex object containing `S` dimensional [`f32`] vectors, sized at runtime (allocated on the heap on the cpu side)
+pub struct BoxedF32VO<const S: usize> {
     vbo: gl::types::GLuint,
     vao: gl::types::GLuint,
-    pub data: [[f32; S]; N],
+    pub data: Box<[[f32; S]]>,
 }
-impl<const L: usize, const S: usize> F32VO<L, S> {
-    pub fn new(data: [[f32; S]; L]) -> Self {
+impl<const S: usize> BoxedF32VO<S> {
+    pub fn new(len: usize) -> Self {
         let mut self_ = Self {
             vbo: 0,
             vao: 0,
-            data,
+            data: vec![[0.0; S]; len].into_boxed_slice(),
         };
         // Create and bind the vertex buffer
         unsafe {
@@ -368,9 +471,9 @@ impl<const L: usize, const S: usize> F32VO<L, S> {
             gl::BindBuffer(gl::ARRAY_BUFFER, self_.vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (L * S * std::mem::size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-                &self_.data[0] as *const f32 as *const c_void,
-                gl::STATIC_DRAW,
+                self_.byte_len(0..len) as gl::types::GLsizeiptr,
+                self_.data.as_ptr() as *const c_void,
+                gl::DYNAMIC_DRAW,
             );
         }
 
@@ -391,90 +494,24 @@ impl<const L: usize, const S: usize> F32VO<L, S> {
 
         self_
     }
-    pub fn update(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (L * S * std::mem::size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-                &self.data[0] as *const f32 as *const c_void,
-                gl::STATIC_DRAW,
-            );
-        }
+    pub fn len(&self) -> usize {
+        self.data.len()
     }
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.vao);
-            gl::EnableVertexAttribArray(0);
-        }
-    }
-}
-impl<const L: usize, const S: usize> Drop for F32VO<L, S> {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &self.vbo);
-            gl::DeleteVertexArrays(1, &self.vao);
-        }
-    }
-}
-
-/// A vertex object containing `N` `S` dimensional [`f32`] vectors (allocated on the heap on the cpu side)
-pub struct BoxedF32VO<const N: usize, const S: usize> {
-    vbo: gl::types::GLuint,
-    vao: gl::types::GLuint,
-    pub data: Box<[[f32; S]; N]>,
-}
-impl<const L: usize, const S: usize> BoxedF32VO<L, S> {
-    pub fn new() -> Self {
-        let mut self_ = Self {
-            vbo: 0,
-            vao: 0,
-            data: unsafe {
-                // Memory safety: overwritten with all float zeros directly after.
-                let mut b: Box<[[f32; S]; L]> = Box::from_raw(std::alloc::alloc(
-                    std::alloc::Layout::new::<[[f32; S]; L]>(),
-                ) as *mut _);
-                b.fill([0.0; S]);
-                b
-            },
-        };
-        // Create and bind the vertex buffer
-        unsafe {
-            gl::GenBuffers(1, &mut self_.vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self_.vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (L * S * std::mem::size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-                self_.data.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
-        }
-
-        // Create and bind the vertex array object
-        unsafe {
-            gl::GenVertexArrays(1, &mut self_.vao);
-            gl::BindVertexArray(self_.vao);
-            gl::VertexAttribPointer(
-                0,
-                S as gl::types::GLint,
-                gl::FLOAT,
-                gl::FALSE,
-                (S * std::mem::size_of::<gl::types::GLfloat>()) as gl::types::GLsizei,
-                std::ptr::null(),
-            );
-            gl::EnableVertexAttribArray(0);
-        }
-
-        self_
+    fn byte_len(&self, range: Range<usize>) -> usize {
+        (range.end - range.start) * S * std::mem::size_of::<gl::types::GLfloat>()
     }
     pub fn update(&self) {
+        self.update_range(0..self.len());
+    }
+    /// Upload only the vectors in `range`, leaving the rest of the gpu buffer untouched.
+    pub fn update_range(&self, range: Range<usize>) {
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            gl::BufferData(
+            gl::BufferSubData(
                 gl::ARRAY_BUFFER,
-                (L * S * std::mem::size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-                &self.data[0] as *const f32 as *const c_void,
-                gl::STATIC_DRAW,
+                self.byte_len(0..range.start) as gl::types::GLintptr,
+                self.byte_len(range.clone()) as gl::types::GLsizeiptr,
+                self.data[range].as_ptr() as *const c_void,
             );
         }
     }
@@ -485,7 +522,7 @@ impl<const L: usize, const S: usize> BoxedF32VO<L, S> {
         }
     }
 }
-impl<const L: usize, const S: usize> Drop for BoxedF32VO<L, S> {
+impl<const S: usize> Drop for BoxedF32VO<S> {
     fn drop(&mut self) {
         unsafe {
             gl::DeleteBuffers(1, &self.vbo);
@@ -517,6 +554,7 @@ macro_rules! glrs_renderable {
             $( $field_vis $field: $field_ty, )*
         }
         impl $struct_name {
+            #[allow(clippy::new_without_default)]
             pub fn new(
                 $( $newarg : $newarg_ty, )*
             ) -> Self {
@@ -525,12 +563,12 @@ macro_rules! glrs_renderable {
                     let builder = glrs::GLShaderProgramBuilder::new();
                     let vert = glrs::GLShader::load(
                         glrs::GLShaderType::Vertex,
-                        include_str!($sh_vert),
+                        &$crate::render::shader_source(include_str!($sh_vert)),
                     )
                     .unwrap();
                     let frag = glrs::GLShader::load(
                         glrs::GLShaderType::Fragment,
-                        include_str!($sh_frag),
+                        &$crate::render::shader_source(include_str!($sh_frag)),
                     )
                     .unwrap();
 
@@ -596,7 +634,7 @@ impl<T: Into<gl::types::GLint> + std::ops::Sub<Output = V> + Copy, V: Into<gl::t
         unsafe {
             gl::DrawArrays(
                 mode,
-                range.start.into(),
+                range.start.into() * stride,
                 (range.end - range.start).into() * stride,
             )
         }
@@ -631,12 +669,16 @@ impl TransparencyMode {
 
 pub enum GLParam {
     V1F(gl::types::GLfloat),
+    V2F(gl::types::GLfloat, gl::types::GLfloat),
+    V4F(Rgba<gl::types::GLfloat>),
 }
 #[inline]
 pub fn uniform(location: gl::types::GLint, value: GLParam) {
     unsafe {
         match value {
             GLParam::V1F(v) => gl::Uniform1f(location, v),
+            GLParam::V2F(x, y) => gl::Uniform2f(location, x, y),
+            GLParam::V4F(Rgba { r, g, b, a }) => gl::Uniform4f(location, r, g, b, a),
         }
     }
 }
@@ -0,0 +1,180 @@
+use std::ops::Range;
+
+use super::{decode_level, encode_level, FrequencyMapping, GRID_ROWS};
+
+/// Past frames kept on the CPU, for scrolling back through while paused and reading back
+/// what is under the cursor: each channel's level per row of the unzoomed display, packed
+/// like [`super::RenderGrid`]'s textures, and its fundamental. Holds up to `capacity`
+/// frames, dropping the oldest, and only allocates them as they arrive.
+pub struct MagnitudeHistory {
+    channels: usize,
+    capacity: usize,
+    /// Indexed by slot, then channel, then row.
+    levels: Vec<u16>,
+    /// Fundamental and confidence, indexed by slot, then channel.
+    pitch: Vec<[f32; 2]>,
+    /// Slot of the newest frame.
+    newest: usize,
+    len: usize,
+}
+impl MagnitudeHistory {
+    pub fn new(channels: usize, capacity: usize) -> Self {
+        Self {
+            channels,
+            capacity: capacity.max(1),
+            levels: vec![],
+            pitch: vec![],
+            newest: 0,
+            len: 0,
+        }
+    }
+    /// Frames stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Start a new, silent frame, dropping the oldest once full. The `set_` methods write
+    /// to it.
+    pub fn push(&mut self) {
+        if self.len < self.capacity {
+            self.levels
+                .resize((self.len + 1) * self.channels * GRID_ROWS, 0);
+            self.pitch.resize((self.len + 1) * self.channels, [0.0; 2]);
+            self.newest = self.len;
+            self.len += 1;
+        } else {
+            self.newest = (self.newest + 1) % self.capacity;
+            let start = self.newest * self.channels;
+            self.levels[start * GRID_ROWS..(start + self.channels) * GRID_ROWS].fill(0);
+            self.pitch[start..start + self.channels].fill([0.0; 2]);
+        }
+    }
+    /// Index of channel `ch` of the frame `age` frames before the newest, if still stored.
+    fn index(&self, ch: usize, age: usize) -> Option<usize> {
+        if ch >= self.channels || age >= self.len {
+            return None;
+        }
+        let slot = (self.newest + self.capacity - age) % self.capacity;
+        Some(slot * self.channels + ch)
+    }
+    fn newest_rows_mut(&mut self, ch: usize) -> &mut [u16] {
+        let start = self.index(ch, 0).expect("push a frame first") * GRID_ROWS;
+        &mut self.levels[start..start + GRID_ROWS]
+    }
+    /// Store channel `ch` of the newest frame from display rows already resampled and
+    /// packed.
+    pub fn set_rows(&mut self, ch: usize, rows: &[u16]) {
+        self.newest_rows_mut(ch).copy_from_slice(rows);
+    }
+    /// Store channel `ch` of the newest frame from points at arbitrary frequencies, like the
+    /// reassigned spectrum's, keeping the loudest point in each row.
+    pub fn set_points(
+        &mut self,
+        ch: usize,
+        mapping: FrequencyMapping,
+        freqs: &[f32],
+        magnitude: impl Iterator<Item = f32>,
+    ) {
+        let column = self.newest_rows_mut(ch);
+        for (&freq, magnitude) in freqs.iter().zip(magnitude) {
+            let row = mapping.to_display(freq) * GRID_ROWS as f32;
+            if (0.0..GRID_ROWS as f32).contains(&row) {
+                let level = &mut column[row as usize];
+                *level = (*level).max(encode_level(magnitude));
+            }
+        }
+    }
+    /// Store channel `ch`'s fundamental of the newest frame and how sure it is.
+    pub fn set_pitch(&mut self, ch: usize, freq: f32, confidence: f32) {
+        let i = self.index(ch, 0).expect("push a frame first");
+        self.pitch[i] = [freq, confidence];
+    }
+    /// Packed levels of channel `ch`, `age` frames before the newest.
+    pub fn rows(&self, ch: usize, age: usize) -> Option<&[u16]> {
+        let start = self.index(ch, age)? * GRID_ROWS;
+        Some(&self.levels[start..start + GRID_ROWS])
+    }
+    /// Fundamental and confidence of channel `ch`, `age` frames before the newest.
+    pub fn pitch(&self, ch: usize, age: usize) -> Option<[f32; 2]> {
+        Some(self.pitch[self.index(ch, age)?])
+    }
+    /// Magnitude of channel `ch`, `age` frames before the newest: the loudest of the rows
+    /// between heights `y` of the unzoomed display.
+    pub fn get(&self, ch: usize, age: usize, y: Range<f32>) -> f32 {
+        let Some(rows) = self.rows(ch, age) else {
+            return 0.0;
+        };
+        let row = |y: f32| ((y * GRID_ROWS as f32).max(0.0) as usize).min(GRID_ROWS - 1);
+        let loudest = rows[row(y.start)..=row(y.end)].iter().copied().max();
+        decode_level(loudest.unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Push a frame with every row of channel `ch` at `level` and its pitch at `level`.
+    fn push_tagged(history: &mut MagnitudeHistory, level: u16) {
+        history.push();
+        for ch in 0..history.channels {
+            history.set_rows(ch, &[level + ch as u16; GRID_ROWS]);
+            history.set_pitch(ch, level as f32, ch as f32);
+        }
+    }
+
+    #[test]
+    fn reads_back_frames_by_age_past_capacity() {
+        let (channels, capacity) = (2, 3);
+        let mut history = MagnitudeHistory::new(channels, capacity);
+        assert_eq!(history.rows(0, 0), None);
+        for newest in 1..=8 {
+            push_tagged(&mut history, 10 * newest);
+            assert_eq!(history.len(), (newest as usize).min(capacity));
+            for age in 0..history.len() {
+                let level = 10 * (newest - age as u16);
+                for ch in 0..channels {
+                    let rows = history.rows(ch, age).unwrap();
+                    assert!(rows.iter().all(|&it| it == level + ch as u16), "age {age}");
+                    assert_eq!(history.pitch(ch, age), Some([level as f32, ch as f32]));
+                }
+            }
+            assert_eq!(history.rows(0, history.len()), None);
+            assert_eq!(history.pitch(0, history.len()), None);
+            assert_eq!(history.rows(channels, 0), None);
+        }
+    }
+
+    #[test]
+    fn reused_slot_is_silent() {
+        let mut history = MagnitudeHistory::new(1, 2);
+        push_tagged(&mut history, 10);
+        push_tagged(&mut history, 20);
+        // reuses the first frame's slot
+        history.push();
+        assert!(history.rows(0, 0).unwrap().iter().all(|&it| it == 0));
+        assert_eq!(history.pitch(0, 0), Some([0.0; 2]));
+        assert!(history.rows(0, 1).unwrap().iter().all(|&it| it == 20));
+        assert_eq!(history.rows(0, 2), None);
+    }
+
+    #[test]
+    fn get_takes_the_loudest_row_in_range() {
+        let mut history = MagnitudeHistory::new(1, 4);
+        history.push();
+        let mut rows = [encode_level(1e-4); GRID_ROWS];
+        rows[100] = encode_level(0.5);
+        history.set_rows(0, &rows);
+        let y = |row: f32| row / GRID_ROWS as f32;
+        let near = |it: f32, expected: f32| (it / expected - 1.0).abs() < 1e-3;
+
+        assert!(near(history.get(0, 0, y(99.0)..y(101.0)), 0.5));
+        assert!(near(history.get(0, 0, y(100.5)..y(100.5)), 0.5));
+        assert!(near(history.get(0, 0, y(101.0)..y(110.0)), 1e-4));
+        assert!(near(history.get(0, 0, y(90.0)..y(99.9)), 1e-4));
+        // heights off the display are clamped to its first and last rows
+        assert!(near(history.get(0, 0, -1.0..2.0), 0.5));
+        assert!(near(history.get(0, 0, 1.5..2.0), 1e-4));
+        // frames not stored read as silence
+        assert_eq!(history.get(0, 1, 0.0..1.0), 0.0);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    audio::{AnalysisMode, AudioDataChunk, FftConfig},
+    util::{RectI, Vec2I},
+};
+
+use super::{
+    color::ColorLut, glrs, ChannelLayout, ColorScale, FrequencyMapping, RenderGrid,
+    RenderReassignedSpectrogram, NUM_SPECTROGRAM_FRAMES,
+};
+
+/// Draws a whole recording's reassigned spectrogram into an offscreen image, the way the
+/// window would show it if it were wide enough.
+///
+/// Hops are collected into pages of [`NUM_SPECTROGRAM_FRAMES`] frames, the size of the live
+/// view's history, and each full page is drawn into its slice of the image.
+pub struct OfflineRenderer {
+    framebuffer: glrs::GLFramebuffer,
+    spectrogram: Option<RenderReassignedSpectrogram>,
+    grid: Option<RenderGrid>,
+    fft_config: FftConfig,
+    channels: usize,
+    mode: AnalysisMode,
+    channel_layout: ChannelLayout,
+    /// Overridden by a grid's own linear band, like in the window.
+    mapping: FrequencyMapping,
+    color_lut: ColorLut,
+    width: usize,
+    height: usize,
+    total_hops: usize,
+    /// Index of the page being filled, and how many of its frames are filled.
+    page: usize,
+    frame: usize,
+}
+impl OfflineRenderer {
+    /// An empty `width` by `height` image that will hold `total_hops` hops side by side.
+    pub fn new(
+        width: usize,
+        height: usize,
+        total_hops: usize,
+        channel_layout: ChannelLayout,
+        mapping: FrequencyMapping,
+        color_scale: ColorScale,
+    ) -> Result<Self, glrs::GLError> {
+        let framebuffer = glrs::GLFramebuffer::new(width, height)?;
+        framebuffer.bind();
+        glrs::viewport(RectI {
+            pos: Vec2I(0, 0),
+            dim: Vec2I(width as i32, height as i32),
+        });
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+        }
+        glrs::Rgba {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        }
+        .gl_clear_color();
+        Ok(Self {
+            framebuffer,
+            spectrogram: None,
+            grid: None,
+            fft_config: FftConfig::default(),
+            channels: 0,
+            mode: AnalysisMode::default(),
+            channel_layout,
+            mapping,
+            color_lut: ColorLut::new(color_scale),
+            width,
+            height,
+            total_hops: total_hops.max(1),
+            page: 0,
+            frame: 0,
+        })
+    }
+
+    pub fn push(&mut self, chunk: &AudioDataChunk) {
+        if self.spectrogram.is_none()
+            || chunk.fft_config != self.fft_config
+            || chunk.channels() != self.channels
+            || chunk.mode != self.mode
+        {
+            self.flush();
+            self.fft_config = chunk.fft_config;
+            self.channels = chunk.channels();
+            self.mode = chunk.mode;
+            self.spectrogram = Some(RenderReassignedSpectrogram::new(
+                self.fft_config,
+                self.channels,
+            ));
+            self.grid = Some(RenderGrid::new(self.channels));
+        }
+        match &chunk.grid {
+            Some(grid) => {
+                if let Some((lo, hi)) = grid.linear_range {
+                    self.mapping = FrequencyMapping::linear(lo, hi);
+                }
+                self.grid
+                    .as_mut()
+                    .unwrap()
+                    .set_wave(self.frame, grid, self.mapping)
+            }
+            None => self
+                .spectrogram
+                .as_mut()
+                .unwrap()
+                .set_wave(self.frame, chunk),
+        }
+        self.frame += 1;
+        if self.frame == NUM_SPECTROGRAM_FRAMES {
+            self.flush();
+        }
+    }
+
+    /// Draw the frames collected so far into the current page's slice of the image.
+    fn flush(&mut self) {
+        let (Some(spectrogram), Some(grid)) = (&self.spectrogram, &self.grid) else {
+            return;
+        };
+        if self.frame == 0 {
+            return;
+        }
+        // a full page's width, even for a partial last page, so hops stay evenly spaced
+        let x = |hop: usize| (hop * self.width) as f64 / self.total_hops as f64;
+        let x0 = x(self.page * NUM_SPECTROGRAM_FRAMES);
+        let x1 = x((self.page + 1) * NUM_SPECTROGRAM_FRAMES);
+        let page_width = x1 - x0;
+        let point_size = (page_width / NUM_SPECTROGRAM_FRAMES as f64).ceil().max(1.0);
+        // room on both sides for points reassigned in time into the neighbouring pages, up
+        // to half a frame away
+        let margin_frames = self.fft_config.half_size() / self.fft_config.stride + 1;
+        let margin = margin_frames as f64 / NUM_SPECTROGRAM_FRAMES as f64;
+        let span = 1.0 + 2.0 * margin;
+
+        self.framebuffer.bind();
+        glrs::viewport(RectI {
+            pos: Vec2I((x0 - margin * page_width).round() as i32, 0),
+            dim: Vec2I((span * page_width).round() as i32, self.height as i32),
+        });
+        let x_range = ((margin / span) as f32, (1.0 / span) as f32);
+        if self.mode.is_grid() {
+            grid.draw(
+                0.0,
+                self.frame,
+                self.channel_layout,
+                &self.color_lut,
+                x_range,
+                (0.0, 1.0),
+            );
+        } else {
+            spectrogram.draw(
+                0.0,
+                self.frame,
+                self.mapping,
+                self.channel_layout,
+                &self.color_lut,
+                x_range,
+                point_size as f32,
+            );
+        }
+        self.page += 1;
+        self.frame = 0;
+    }
+
+    /// Draw anything still pending and read the image back as 8 bit RGBA, top row first.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.flush();
+        self.framebuffer
+            .read_pixels()
+            .iter()
+            .flat_map(|&glrs::Rgba { r, g, b, a }| [r, g, b, a])
+            .collect()
+    }
+}
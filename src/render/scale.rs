@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::glrs::{self, GLParam::*};
+
+/// How frequencies are spread over the display's height. Must match `freq_scale.glsl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrequencyScale {
+    Linear,
+    /// Equal height per octave. Frequencies below 1 Hz are drawn at 1 Hz.
+    #[default]
+    Log,
+    /// The mel scale: roughly linear below 700 Hz and logarithmic above.
+    Mel,
+    /// Critical bands of hearing, after Traunmüller.
+    Bark,
+    /// Equivalent rectangular bandwidths of the auditory filters, after Glasberg and Moore.
+    Erb,
+}
+impl FrequencyScale {
+    const ALL: [Self; 5] = [Self::Linear, Self::Log, Self::Mel, Self::Bark, Self::Erb];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Log => "log",
+            Self::Mel => "mel",
+            Self::Bark => "bark",
+            Self::Erb => "erb",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Linear => Self::Log,
+            Self::Log => Self::Mel,
+            Self::Mel => Self::Bark,
+            Self::Bark => Self::Erb,
+            Self::Erb => Self::Linear,
+        }
+    }
+    /// `freq` in Hz on this scale, in its own units.
+    fn warp(&self, freq: f32) -> f32 {
+        match self {
+            Self::Linear => freq,
+            Self::Log => freq.max(1.0).log2(),
+            Self::Mel => 2595.0 * (1.0 + freq / 700.0).log10(),
+            Self::Bark => 26.81 * freq / (1960.0 + freq) - 0.53,
+            Self::Erb => 21.4 * (1.0 + 0.00437 * freq).log10(),
+        }
+    }
+    /// The inverse of [`Self::warp`].
+    fn unwarp(&self, value: f32) -> f32 {
+        match self {
+            Self::Linear => value,
+            Self::Log => value.exp2(),
+            Self::Mel => 700.0 * (10f32.powf(value / 2595.0) - 1.0),
+            Self::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            Self::Erb => (10f32.powf(value / 21.4) - 1.0) / 0.00437,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseFrequencyScaleError(String);
+impl Display for ParseFrequencyScaleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown frequency scale {:?}, expected linear, log, mel, bark or erb",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseFrequencyScaleError {}
+
+impl FromStr for FrequencyScale {
+    type Err = ParseFrequencyScaleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name() == s)
+            .ok_or_else(|| ParseFrequencyScaleError(s.to_string()))
+    }
+}
+
+/// The mapping from frequency to display height shared by every renderer: a scale between
+/// the frequencies at the bottom and top of each channel's band.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyMapping {
+    pub scale: FrequencyScale,
+    pub min_freq: f32,
+    pub max_freq: f32,
+}
+impl Default for FrequencyMapping {
+    fn default() -> Self {
+        // ten octaves from just below 20 Hz
+        Self {
+            scale: FrequencyScale::default(),
+            min_freq: 4.25f32.exp2(),
+            max_freq: 14.25f32.exp2(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseFrequencyRangeError(String);
+impl Display for ParseFrequencyRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid frequency range {:?}, expected <min hz>-<max hz> like \"20-20000\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseFrequencyRangeError {}
+
+impl FrequencyMapping {
+    /// Spread `min_freq..max_freq` linearly, as for an analysis of one narrow band.
+    pub fn linear(min_freq: f32, max_freq: f32) -> Self {
+        Self {
+            scale: FrequencyScale::Linear,
+            min_freq,
+            max_freq,
+        }
+    }
+    /// Parse the range, `min-max`, keeping the scale.
+    pub fn with_range(self, s: &str) -> Result<Self, ParseFrequencyRangeError> {
+        let err = || ParseFrequencyRangeError(s.to_string());
+        let (min, max) = s.split_once('-').ok_or_else(err)?;
+        let mapping = Self {
+            min_freq: min.trim().parse().map_err(|_| err())?,
+            max_freq: max.trim().parse().map_err(|_| err())?,
+            ..self
+        };
+        if !(mapping.min_freq >= 0.0 && mapping.max_freq > mapping.min_freq) {
+            return Err(err());
+        }
+        Ok(mapping)
+    }
+    /// Height of `freq` (in Hz) as a fraction of a band, 0 at `min_freq` and 1 at
+    /// `max_freq`.
+    pub fn to_display(&self, freq: f32) -> f32 {
+        let (lo, hi) = (
+            self.scale.warp(self.min_freq),
+            self.scale.warp(self.max_freq),
+        );
+        (self.scale.warp(freq) - lo) / (hi - lo)
+    }
+    /// The frequency in Hz at height `y` of a band, the inverse of [`Self::to_display`].
+    pub fn to_freq(&self, y: f32) -> f32 {
+        let (lo, hi) = (
+            self.scale.warp(self.min_freq),
+            self.scale.warp(self.max_freq),
+        );
+        self.scale.unwarp(lo + (hi - lo) * y)
+    }
+    /// Set the uniforms `freq_scale.glsl` reads, for the bound shader.
+    pub(super) fn set_uniforms(&self) {
+        glrs::uniform(10, V1F(self.scale as u32 as f32));
+        glrs::uniform(11, V2F(self.min_freq, self.max_freq));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_freq_inverts_to_display() {
+        for scale in FrequencyScale::ALL {
+            for (min_freq, max_freq) in [(20.0, 20000.0), (1.0, 24000.0), (440.0, 880.0)] {
+                let mapping = FrequencyMapping {
+                    scale,
+                    min_freq,
+                    max_freq,
+                };
+                assert!(mapping.to_display(min_freq).abs() < 1e-5);
+                assert!((mapping.to_display(max_freq) - 1.0).abs() < 1e-5);
+                // every 1/12 octave across the range
+                let freqs = (0..).map(|i| min_freq * (i as f32 / 12.0).exp2());
+                for freq in freqs.take_while(|&it| it <= max_freq) {
+                    let round_trip = mapping.to_freq(mapping.to_display(freq));
+                    let error = (round_trip / freq - 1.0).abs();
+                    assert!(
+                        error < 1e-4,
+                        "{}: {freq} Hz -> {round_trip} Hz",
+                        scale.name()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use super::{FrequencyMapping, NUM_SPECTROGRAM_FRAMES};
+
+/// Narrowest frequency range zoomed into, as a fraction of the full display.
+const MIN_FREQ_SPAN: f32 = 1.0 / 256.0;
+/// Fewest frames zoomed into across the width while paused.
+const MIN_TIME_SPAN: f32 = 32.0;
+
+/// The part of the display zoomed into, changed with the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewState {
+    /// Bottom and top of the visible frequency range, as heights of the unzoomed display.
+    freq: (f32, f32),
+    /// Frames between the right edge and the newest frame, and frames across the width.
+    /// Only changed while paused, counting back from the moment of pausing.
+    time: (f32, f32),
+}
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            freq: (0.0, 1.0),
+            time: (0.0, NUM_SPECTROGRAM_FRAMES as f32),
+        }
+    }
+}
+impl ViewState {
+    /// Zoom the frequency range by `factor` (below 1 to zoom in), keeping height `anchor` of
+    /// the view (0 at the bottom, 1 at the top) in place.
+    pub fn zoom_freq(&mut self, anchor: f32, factor: f32) {
+        let (lo, hi) = self.freq;
+        let anchor = anchor.clamp(0.0, 1.0);
+        let centre = lo + (hi - lo) * anchor;
+        let span = ((hi - lo) * factor).clamp(MIN_FREQ_SPAN, 1.0);
+        self.freq = clamp_range(centre - span * anchor, span);
+    }
+    /// Move the frequency range up by `delta` view heights.
+    pub fn pan_freq(&mut self, delta: f32) {
+        let (lo, hi) = self.freq;
+        self.freq = clamp_range(lo + (hi - lo) * delta, hi - lo);
+    }
+    /// The visible frequency range's bottom and height, as heights of the unzoomed display.
+    pub fn freq_view(&self) -> (f32, f32) {
+        (self.freq.0, self.freq.1 - self.freq.0)
+    }
+    /// Zoom the time range by `factor` (below 1 to zoom in), keeping width `anchor` of the
+    /// view (0 at the left, 1 at the right) in place, within the `frames` there are.
+    pub fn zoom_time(&mut self, anchor: f32, factor: f32, frames: usize) {
+        let (scroll, span) = self.time;
+        let behind = 1.0 - anchor.clamp(0.0, 1.0);
+        let age = scroll + span * behind;
+        let span = (span * factor).clamp(MIN_TIME_SPAN, max_span(frames));
+        self.time = (clamp_scroll(age - span * behind, span, frames), span);
+    }
+    /// Move the time range back by `delta` view widths, within the `frames` there are.
+    pub fn pan_time(&mut self, delta: f32, frames: usize) {
+        let (scroll, span) = self.time;
+        self.time = (clamp_scroll(scroll + span * delta, span, frames), span);
+    }
+    /// Go back to the newest frames at their usual width.
+    pub fn reset_time(&mut self) {
+        self.time = Self::default().time;
+    }
+    /// Frames between the right edge and the newest frame, and frames across the width.
+    pub fn time_window(&self) -> (f32, f32) {
+        self.time
+    }
+    /// Whether time is neither zoomed nor scrolled back, as just after pausing.
+    pub fn time_is_default(&self) -> bool {
+        self.time == Self::default().time
+    }
+    /// `mapping` narrowed to the visible frequency range.
+    pub fn mapping(&self, mapping: FrequencyMapping) -> FrequencyMapping {
+        FrequencyMapping {
+            min_freq: mapping.to_freq(self.freq.0),
+            max_freq: mapping.to_freq(self.freq.1),
+            ..mapping
+        }
+    }
+}
+
+/// Widest time range for `frames` frames of history, never narrower than the live display.
+fn max_span(frames: usize) -> f32 {
+    frames.max(NUM_SPECTROGRAM_FRAMES) as f32
+}
+
+/// `scroll` kept from going past the oldest of `frames` frames with `span` visible.
+fn clamp_scroll(scroll: f32, span: f32, frames: usize) -> f32 {
+    scroll.clamp(0.0, (max_span(frames) - span).max(0.0))
+}
+
+/// `span` long from `start`, moved to lie within 0 to 1.
+fn clamp_range(start: f32, span: f32) -> (f32, f32) {
+    let start = start.clamp(0.0, 1.0 - span);
+    (start, start + span)
+}
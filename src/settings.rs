@@ -0,0 +1,60 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Choices remembered between runs, stored as `key=value` lines in
+/// `$XDG_CONFIG_HOME/spexia/settings` (or `~/.config/spexia/settings`).
+#[derive(Debug, Default)]
+pub struct Settings {
+    values: BTreeMap<String, String>,
+}
+impl Settings {
+    /// Read the settings file. A missing or unreadable file gives empty settings.
+    pub fn load() -> Self {
+        let mut values = BTreeMap::new();
+        if let Some(text) = Self::path().and_then(|path| std::fs::read_to_string(path).ok()) {
+            for line in text.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.split_once('=') {
+                    Some((key, value)) => {
+                        values.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                    None => eprintln!("ignoring malformed settings line {:?}", line),
+                }
+            }
+        }
+        Self { values }
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "neither XDG_CONFIG_HOME nor HOME is set",
+            ));
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect();
+        std::fs::write(path, text)
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|it| it.as_str())
+    }
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    fn path() -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|it| !it.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("spexia").join("settings"))
+    }
+}
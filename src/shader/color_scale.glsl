@@ -0,0 +1,31 @@
+// the colour scale shared by the spectrogram shaders, see ColorLut in render/color.rs:
+// the colormap from quietest to loudest, as one row
+layout(location = 12) uniform sampler2D colormap;
+// dBFS drawn in the first and last colour
+layout(location = 13) uniform vec2 db_range;
+layout(location = 14) uniform float gamma;
+
+// 0 at the floor to 1 at the ceiling, curved by gamma
+float db_to_level(float db) {
+    return pow(clamp((db - db_range.x) / (db_range.y - db_range.x), 0.0, 1.0), gamma);
+}
+
+vec3 colormap_color(float level) {
+    // between the first and last texel centres
+    float n = float(textureSize(colormap, 0).x);
+    return texture(colormap, vec2((level * (n - 1.0) + 0.5) / n, 0.5)).rgb;
+}
+
+vec3 hsv2rgb(vec3 c) {
+    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+}
+
+// instead of the colormap when channels are overlaid: each channel in its own hue, red, cyan,
+// then hues in between, brightening with the level much like the heat colormap
+vec3 channel_color(float level, float channel) {
+    float hue = fract(channel * 0.5 + floor(channel * 0.5) * 0.25);
+    float brightness = 0.9 * level * level + 0.15 * min(level * 3.0, 1.0);
+    return hsv2rgb(vec3(hue, 1.0, 1.0)) * brightness;
+}
\ No newline at end of file
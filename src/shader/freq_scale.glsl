@@ -0,0 +1,36 @@
+// the frequency to display height mapping shared by the renderers, see FrequencyMapping in
+// render/scale.rs: 0 linear, 1 log, 2 mel, 3 bark, 4 erb
+layout(location = 10) uniform float freq_scale;
+// frequencies in Hz at the bottom and top of a band
+layout(location = 11) uniform vec2 freq_range;
+
+float warp_freq(float freq) {
+    switch (int(freq_scale)) {
+        case 0: return freq;
+        case 1: return log2(max(freq, 1.0));
+        case 2: return 2595.0 * log(1.0 + freq / 700.0) / log(10.0);
+        case 3: return 26.81 * freq / (1960.0 + freq) - 0.53;
+        default: return 21.4 * log(1.0 + 0.00437 * freq) / log(10.0);
+    }
+}
+float unwarp_freq(float value) {
+    switch (int(freq_scale)) {
+        case 0: return value;
+        case 1: return exp2(value);
+        case 2: return 700.0 * (pow(10.0, value / 2595.0) - 1.0);
+        case 3: return 1960.0 * (value + 0.53) / (26.28 - value);
+        default: return (pow(10.0, value / 21.4) - 1.0) / 0.00437;
+    }
+}
+// height of freq as a fraction of a band
+float freq_to_display(float freq) {
+    float lo = warp_freq(freq_range.x);
+    float hi = warp_freq(freq_range.y);
+    return (warp_freq(freq) - lo) / (hi - lo);
+}
+// frequency at a height of a band
+float display_to_freq(float y) {
+    float lo = warp_freq(freq_range.x);
+    float hi = warp_freq(freq_range.y);
+    return unwarp_freq(mix(lo, hi, y));
+}
\ No newline at end of file
@@ -0,0 +1,102 @@
+use std::fmt::Display;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// A note of the equal-tempered scale, as a MIDI note number (A4 is 69, middle C 60).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Note(pub i32);
+impl Note {
+    pub const A4: Self = Self(69);
+
+    /// Whether this is a C, where octave numbers change.
+    pub fn is_c(&self) -> bool {
+        self.0.rem_euclid(12) == 0
+    }
+}
+impl Display for Note {
+    /// Scientific pitch notation, like `A4` or `C#-1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = NOTE_NAMES[self.0.rem_euclid(12) as usize];
+        write!(f, "{}{}", name, self.0.div_euclid(12) - 1)
+    }
+}
+
+/// Twelve-tone equal temperament relative to a reference pitch for A4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    /// Frequency of A4 in Hz.
+    pub a4: f32,
+}
+impl Default for Tuning {
+    fn default() -> Self {
+        Self { a4: 440.0 }
+    }
+}
+impl Tuning {
+    pub fn new(a4: f32) -> Self {
+        Self { a4 }
+    }
+    /// Frequency of `note` in Hz.
+    pub fn freq(&self, note: Note) -> f32 {
+        self.a4 * ((note.0 - Note::A4.0) as f32 / 12.0).exp2()
+    }
+    /// The note nearest to `freq` (in Hz), and how far `freq` is from it in cents.
+    pub fn nearest(&self, freq: f32) -> (Note, f32) {
+        let semitones = 12.0 * (freq / self.a4).log2();
+        let nearest = semitones.round();
+        (
+            Note(Note::A4.0 + nearest as i32),
+            100.0 * (semitones - nearest),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_notes_in_scientific_pitch_notation() {
+        let names = [
+            (69, "A4"),
+            (60, "C4"),
+            (61, "C#4"),
+            (71, "B4"),
+            (0, "C-1"),
+            (-1, "B-2"),
+        ];
+        for (note, name) in names {
+            assert_eq!(Note(note).to_string(), name);
+        }
+    }
+
+    #[test]
+    fn finds_nearest_note_and_cents() {
+        let tuning = Tuning::default();
+        assert_eq!(tuning.nearest(440.0), (Note::A4, 0.0));
+        let (note, cents) = tuning.nearest(261.63);
+        assert_eq!(note, Note(60));
+        assert!(cents.abs() < 0.1, "{cents} cents");
+        // just under a quarter tone above A4 rounds down to it, just over up to A#4
+        let (note, cents) = tuning.nearest(440.0 * (0.49f32 / 12.0).exp2());
+        assert_eq!(note, Note::A4);
+        assert!((cents - 49.0).abs() < 0.01, "{cents} cents");
+        let (note, cents) = tuning.nearest(440.0 * (0.51f32 / 12.0).exp2());
+        assert_eq!(note, Note(70));
+        assert!((cents + 49.0).abs() < 0.01, "{cents} cents");
+    }
+
+    #[test]
+    fn nearest_inverts_freq() {
+        for a4 in [415.0, 440.0, 442.0] {
+            let tuning = Tuning::new(a4);
+            for note in (0..128).map(Note) {
+                let (nearest, cents) = tuning.nearest(tuning.freq(note));
+                assert_eq!(nearest, note);
+                assert!(cents.abs() < 0.01, "{note} at A4 = {a4}: {cents} cents");
+            }
+        }
+    }
+}